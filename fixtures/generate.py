@@ -0,0 +1,466 @@
+#!/usr/bin/env python3
+"""Regenerates the sqlite fixtures used by the unit tests.
+
+The data is derived from the row number only so that re-running this script
+produces the same rows every time.
+"""
+import ctypes
+import ctypes.util
+import os
+import sqlite3
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+COUNTRIES = ["usa", "canada", "uk", "india", "germany", "france", "japan", "brazil"]
+INDUSTRIES = ["software", "retail", "banking", "health", "energy"]
+
+
+def fresh(name):
+    path = os.path.join(HERE, name)
+    if os.path.exists(path):
+        os.remove(path)
+    return sqlite3.connect(path)
+
+
+def companies():
+    conn = fresh("companies.db")
+    conn.execute(
+        """CREATE TABLE companies
+(
+\tid integer primary key autoincrement,
+\tname text,
+\tcountry text,
+\tindustry text,
+\tyear_founded integer,
+\temployees integer
+)"""
+    )
+    conn.execute("CREATE INDEX idx_companies_country\n\ton companies (country)")
+    rows = []
+    for i in range(1, 1001):
+        country_index = (i * 7) % len(COUNTRIES) if i % 13 else 0
+        country = COUNTRIES[country_index]
+        # Each country only spans a subset of the industries
+        industry = INDUSTRIES[(i // 8) % (country_index % len(INDUSTRIES) + 1)]
+        rows.append((i, "company %d" % i, country, industry, 1900 + (i * 37) % 120, (i * 53) % 5000))
+    conn.executemany("INSERT INTO companies VALUES (?, ?, ?, ?, ?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def large_rowids():
+    conn = fresh("large_rowids.db")
+    conn.execute("CREATE TABLE items (id integer primary key, code text)")
+    conn.execute("CREATE INDEX idx_items_code on items (code)")
+    # Rowids stored with 1, 4, 6 and 8 byte integer serial types in the index records
+    rows = [(5, "small"), (9000000, "medium"), (3000000000, "big"), (1 << 40, "huge")]
+    rows += [(1 << 40 + i, "filler %d" % i) for i in range(1, 10)]
+    conn.executemany("INSERT INTO items VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def body(row_id, length):
+    return "".join(chr(ord("a") + (row_id + j) % 26) for j in range(length))
+
+
+def overflow():
+    conn = fresh("overflow.db")
+    conn.execute("PRAGMA page_size = 1024")
+    conn.execute("CREATE TABLE docs (id integer primary key, body text)")
+    # With 1024 byte pages a table cell keeps payloads of up to 1024 - 35 = 989 bytes local.
+    # A row's payload is a 4 byte record header plus the body, so rows 1 to 3 sit right at
+    # that boundary and row 4 spans several overflow pages.
+    lengths = [984, 985, 986, 5000]
+    rows = [(i, body(i, length)) for i, length in enumerate(lengths, start=1)]
+    conn.executemany("INSERT INTO docs VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def pk_second():
+    conn = fresh("pk_second.db")
+    # The INTEGER PRIMARY KEY is an alias for the rowid. Its slot in the record still exists
+    # (always NULL), so it doesn't shift the columns declared after it.
+    conn.execute("CREATE TABLE people (name text, id integer primary key, age integer)")
+    conn.executemany(
+        "INSERT INTO people VALUES (?, ?, ?)",
+        [("ada", 10, 36), ("grace", 20, 85), ("linus", 30, 54)],
+    )
+    conn.commit()
+    conn.close()
+
+
+def many_tables():
+    conn = fresh("many_tables.db")
+    conn.execute("PRAGMA page_size = 1024")
+    # Enough CREATE TABLE statements that the schema table no longer fits on page 1 and
+    # becomes an interior page with leaf children
+    for i in range(40):
+        name = "table_" + chr(ord("a") + i // 26) + chr(ord("a") + i % 26)
+        conn.execute("CREATE TABLE %s (id integer primary key, label text)" % name)
+        conn.execute("INSERT INTO %s VALUES (?, ?)" % name, (i, "row of %s" % name))
+    conn.commit()
+    conn.close()
+
+
+def readings():
+    conn = fresh("readings.db")
+    # Known values for the application defined header fields
+    conn.execute("PRAGMA application_id = 1234567")
+    conn.execute("PRAGMA user_version = 42")
+    # Columns with NULLs: `value` has some, `calibration` is NULL in every row
+    conn.execute(
+        "CREATE TABLE readings (id integer primary key, sensor text, value real, calibration integer)"
+    )
+    rows = [
+        (1, "north", 1.5, None),
+        (2, "north", None, None),
+        (3, "south", 4, None),
+        (4, "south", 2.25, None),
+        (5, "east", None, None),
+    ]
+    conn.executemany("INSERT INTO readings VALUES (?, ?, ?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def long_keys():
+    conn = fresh("long_keys.db")
+    conn.execute("PRAGMA page_size = 1024")
+    conn.execute("CREATE TABLE notes (id integer primary key, title text)")
+    conn.execute("CREATE INDEX idx_notes_title on notes (title)")
+    # With 1024 byte pages an index cell keeps at most 230 payload bytes locally, so most of
+    # these keys continue on overflow pages, in leaf as well as interior index cells
+    rows = [(i, "title %02d " % i + body(i, 40 + i * 97)) for i in range(1, 31)]
+    conn.executemany("INSERT INTO notes VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def library():
+    conn = fresh("library.db")
+    # Two tables to join on books.author_id = authors.id. Both have an `id` column.
+    conn.execute("CREATE TABLE authors (id integer primary key, name text, country text)")
+    conn.execute(
+        "CREATE TABLE books (id integer primary key, title text, author_id integer, year integer)"
+    )
+    conn.execute("CREATE INDEX idx_books_author_id ON books (author_id)")
+    authors = [
+        (1, "Jane Austen", "uk"),
+        (2, "Ernest Hemingway", "us"),
+        (3, "George Orwell", "uk"),
+        (4, "Toni Morrison", "us"),
+        (5, "Chinua Achebe", "ng"),  # has no books
+    ]
+    books = [
+        (1, "Pride and Prejudice", 1, 1813),
+        (2, "Emma", 1, 1815),
+        (3, "The Sun Also Rises", 2, 1926),
+        (4, "Animal Farm", 3, 1945),
+        (5, "Nineteen Eighty-Four", 3, 1949),
+        (6, "Beloved", 4, 1987),
+        (7, "The Old Man and the Sea", 2, 1952),
+        (8, "Anonymous Pamphlet", None, 1950),  # has no author
+        (9, "Persuasion", 1, 1817),
+        (10, "Jazz", 4, 1992),
+    ]
+    conn.executemany("INSERT INTO authors VALUES (?, ?, ?)", authors)
+    conn.executemany("INSERT INTO books VALUES (?, ?, ?, ?)", books)
+    conn.commit()
+    conn.close()
+
+
+def text_values():
+    conn = fresh("text_values.db")
+    # Text that needs care when printed
+    conn.execute("CREATE TABLE labels (id integer primary key, label text)")
+    rows = [
+        (1, "plain"),
+        (2, "nul\0inside"),
+        (3, 'tab\tand "quote"'),
+        (4, ""),  # empty text, which isn't NULL
+        (5, None),
+    ]
+    conn.executemany("INSERT INTO labels VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def desc_index():
+    conn = fresh("desc_index.db")
+    conn.execute("PRAGMA page_size = 1024")
+    conn.execute("CREATE TABLE events (id integer primary key, name text)")
+    # The index keys are stored in descending order. Enough of them for an interior root page.
+    conn.execute("CREATE INDEX idx_events_name ON events (name DESC)")
+    rows = [(i, "event %03d %s" % (i * 7 % 200, "x" * 20)) for i in range(1, 201)]
+    conn.executemany("INSERT INTO events VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def deep_table():
+    conn = fresh("deep_table.db")
+    conn.execute("PRAGMA page_size = 1024")
+    conn.execute("CREATE TABLE pages (id integer primary key, content text)")
+    # Two rows fit on a leaf, which makes more leaves than an interior page can point to, so
+    # the table btree has three levels
+    rows = [(i, body(i, 400)) for i in range(1, 301)]
+    conn.executemany("INSERT INTO pages VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def auto_vacuum():
+    conn = fresh("auto_vacuum.db")
+    # Must be set before the first table is created. With a page size of 512 every 103rd page
+    # from page 2 on is a pointer map page.
+    conn.execute("PRAGMA page_size = 512")
+    conn.execute("PRAGMA auto_vacuum = FULL")
+    conn.execute("CREATE TABLE notes (id integer primary key, body text)")
+    conn.execute("CREATE INDEX idx_notes_body ON notes (body)")
+    rows = [(i, body(i, 150)) for i in range(1, 301)]
+    conn.executemany("INSERT INTO notes VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def keywords():
+    conn = fresh("keywords.db")
+    # Columns named after SQL keywords
+    conn.execute(
+        'CREATE TABLE items (id integer primary key, "order" integer, "group" text, count integer)'
+    )
+    rows = [(i, 10 - i, ["a", "b"][i % 2], i * i) for i in range(1, 6)]
+    conn.executemany("INSERT INTO items VALUES (?, ?, ?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def text_pk():
+    conn = fresh("text_pk.db")
+    # Only an INTEGER PRIMARY KEY is the rowid, these keys are stored in the records
+    conn.execute("CREATE TABLE countries (code text primary key, name text)")
+    conn.executemany(
+        "INSERT INTO countries VALUES (?, ?)",
+        [("fr", "France"), ("jp", "Japan"), ("br", "Brazil")],
+    )
+    conn.execute("CREATE TABLE sizes (n int primary key, label text)")
+    conn.executemany(
+        "INSERT INTO sizes VALUES (?, ?)", [(10, "small"), (20, "medium"), (30, "large")]
+    )
+    conn.commit()
+    conn.close()
+
+
+def json_docs():
+    conn = fresh("json_docs.db")
+    conn.execute("CREATE TABLE docs (id integer primary key, data text)")
+    docs = [
+        '{"user": {"name": "ada", "langs": ["en", "fr"]}, "score": 9.5}',
+        '{"user": {"name": "linus", "langs": ["fi"]}, "score": 7}',
+        '{"user": {"name": "grace"}}',
+    ]
+    conn.executemany("INSERT INTO docs (data) VALUES (?)", [(doc,) for doc in docs])
+    conn.commit()
+    conn.close()
+
+
+def composite_index():
+    conn = fresh("composite_index.db")
+    conn.execute("PRAGMA page_size = 1024")
+    conn.execute(
+        "CREATE TABLE orders (id integer primary key, customer text, status text, amount integer)"
+    )
+    # Ordered by customer, and by status from last to first within a customer
+    conn.execute("CREATE INDEX idx_orders_customer_status ON orders (customer, status DESC)")
+    statuses = ["new", "paid", "shipped"]
+    rows = [
+        (i, "customer %02d" % (i * 7 % 20), statuses[i % 3], i * 13 % 500)
+        for i in range(1, 601)
+    ]
+    conn.executemany("INSERT INTO orders VALUES (?, ?, ?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def wide_schema():
+    conn = fresh("wide_schema.db")
+    conn.execute("PRAGMA page_size = 512")
+    # With 60 columns the CREATE TABLE statement is close to 1000 bytes, more than a 512 byte
+    # page keeps local, so its schema row continues on an overflow page.
+    letters = "abcdefghijklmnopqrstuvwxyz"
+    columns = ["column_%s%s text" % (letters[i // 26], letters[i % 26]) for i in range(60)]
+    conn.execute("CREATE TABLE wide (%s)" % ", ".join(columns))
+    conn.executemany(
+        "INSERT INTO wide (column_aa, column_ch) VALUES (?, ?)",
+        [("first", "last"), ("one", "fifty-nine")],
+    )
+    conn.commit()
+    conn.close()
+
+
+def without_rowid():
+    conn = fresh("without_rowid.db")
+    conn.execute("PRAGMA page_size = 1024")
+    # The rows are stored in an index btree keyed by (sku, warehouse), with the key columns
+    # first in each record and the other columns after them. Enough rows for interior pages.
+    conn.execute(
+        "CREATE TABLE stock (warehouse text, quantity integer, sku text, note text, "
+        "PRIMARY KEY (sku, warehouse)) WITHOUT ROWID"
+    )
+    warehouses = ["north", "south", "east"]
+    rows = [
+        (warehouses[i % 3], i * 17 % 100, "sku-%03d" % (i // 3), "note %d" % i if i % 4 else None)
+        for i in range(450)
+    ]
+    conn.executemany("INSERT INTO stock VALUES (?, ?, ?, ?)", rows)
+    conn.execute("CREATE INDEX idx_stock_quantity ON stock (quantity)")
+    # An INTEGER PRIMARY KEY is an ordinary column here, not the rowid
+    conn.execute("CREATE TABLE codes (label text, code integer primary key) WITHOUT ROWID")
+    conn.executemany("INSERT INTO codes VALUES (?, ?)", [("ten", 10), ("two", 2), ("five", 5)])
+    conn.commit()
+    conn.close()
+
+
+def reserved_bytes():
+    path = os.path.join(HERE, "reserved_bytes.db")
+    if os.path.exists(path):
+        os.remove(path)
+    # The sqlite3 module can't reserve space at the end of pages, so the library is called
+    # directly. SQLITE_FCNTL_RESERVE_BYTES (38) applies to a database that is still empty.
+    lib = ctypes.CDLL(ctypes.util.find_library("sqlite3"))
+    db = ctypes.c_void_p()
+    assert lib.sqlite3_open(path.encode(), ctypes.byref(db)) == 0
+    assert lib.sqlite3_file_control(db, b"main", 38, ctypes.byref(ctypes.c_int(32))) == 0
+
+    def execute(sql):
+        assert lib.sqlite3_exec(db, sql.encode(), None, None, None) == 0, sql
+
+    execute("PRAGMA page_size = 1024")
+    execute("CREATE TABLE docs (id integer primary key, title text, body text)")
+    execute("CREATE INDEX idx_docs_title ON docs (title)")
+    # 1024 - 32 = 992 usable bytes per page. Table cells keep up to 992 - 35 = 957 payload
+    # bytes local and index cells up to 222, so most of these rows and index keys overflow.
+    # Rows 41 to 43 fall between the local limits of 992 and 1024 byte pages.
+    lengths = [i * 97 for i in range(1, 41)] + [950, 965, 980]
+    execute("BEGIN")
+    for i, length in enumerate(lengths, start=1):
+        title = "title %02d " % i + body(i, i * 23 % 400)
+        execute("INSERT INTO docs VALUES (%d, '%s', '%s')" % (i, title, body(i, length)))
+    execute("COMMIT")
+    assert lib.sqlite3_close(db) == 0
+
+
+def mixed_types():
+    conn = fresh("mixed_types.db")
+    # A column without a type keeps every value in the storage class it was inserted with
+    conn.execute("CREATE TABLE mixed (id integer primary key, reading)")
+    rows = [
+        (1, 10),
+        (2, "9"),
+        (3, None),
+        (4, b"\x00\xff"),
+        (5, 2.5),
+        (6, "10"),
+        (7, 9),
+        (8, "abc"),
+    ]
+    conn.executemany("INSERT INTO mixed VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+def integer_sizes():
+    conn = fresh("integer_sizes.db")
+    conn.execute("CREATE TABLE numbers (id integer primary key, n integer)")
+    # A view and a trigger have a rootpage of 0, stored with the zero byte serial type 8
+    conn.execute("CREATE VIEW negatives AS SELECT n FROM numbers WHERE n < 0")
+    conn.execute(
+        "CREATE TRIGGER numbers_insert AFTER INSERT ON numbers BEGIN SELECT 1; END"
+    )
+    # Values that need each integer serial type, from the constants 0 and 1 to 8 bytes
+    values = [0, 1, -1, 127, -129, 40000, -8388608, 2147483647, -(1 << 31) - 1]
+    values += [(1 << 47) - 1, -(1 << 47), (1 << 63) - 1, -(1 << 63)]
+    conn.executemany("INSERT INTO numbers (n) VALUES (?)", [(n,) for n in values])
+    conn.commit()
+    conn.close()
+
+
+def names():
+    conn = fresh("names.db")
+    # Names with digits, a leading underscore and a space
+    conn.execute(
+        'CREATE TABLE stats2024 (id integer primary key, col1 integer, _note text, "size range" text)'
+    )
+    rows = [(1, 10, "first", "s-m"), (2, 20, None, "m-l"), (3, 30, "third", "l-xl")]
+    conn.executemany("INSERT INTO stats2024 VALUES (?, ?, ?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+
+def quotes():
+    conn = fresh("quotes.db")
+    # Text with apostrophes, which SQL string literals escape by doubling them
+    conn.execute("CREATE TABLE people (id integer primary key, name text)")
+    rows = [(1, "O'Brien"), (2, "OBrien"), (3, "it''s"), (4, "'")]
+    conn.executemany("INSERT INTO people VALUES (?, ?)", rows)
+    conn.commit()
+    conn.close()
+
+
+
+def text_numbers():
+    conn = fresh("text_numbers.db")
+    # Numeric looking text in TEXT columns compares as text, with or without an index
+    values = ["50", "7", "9", "100", "abc", "55", "3"]
+    for table in ["plain", "indexed"]:
+        conn.execute("CREATE TABLE %s (id integer primary key, v text)" % table)
+        conn.executemany(
+            "INSERT INTO %s (v) VALUES (?)" % table, [(value,) for value in values]
+        )
+    conn.execute("CREATE INDEX idx_indexed_v ON indexed (v)")
+    conn.commit()
+    conn.close()
+
+
+def join_keys():
+    conn = fresh("join_keys.db")
+    # Untyped columns keep the kind of each value, 1.0 and 1 are equal but the text '1' isn't
+    conn.execute("CREATE TABLE floats (id integer primary key, k)")
+    conn.execute("CREATE TABLE ints (id integer primary key, k)")
+    conn.executemany(
+        "INSERT INTO floats (k) VALUES (?)", [(1.0,), (2.5,), (None,), ("1",)]
+    )
+    conn.executemany("INSERT INTO ints (k) VALUES (?)", [(1,), (2,), (None,)])
+    conn.commit()
+    conn.close()
+
+
+if __name__ == "__main__":
+    companies()
+    large_rowids()
+    overflow()
+    pk_second()
+    many_tables()
+    readings()
+    long_keys()
+    library()
+    text_values()
+    desc_index()
+    deep_table()
+    auto_vacuum()
+    keywords()
+    text_pk()
+    json_docs()
+    composite_index()
+    wide_schema()
+    without_rowid()
+    reserved_bytes()
+    mixed_types()
+    integer_sizes()
+    names()
+    quotes()
+    text_numbers()
+    join_keys()
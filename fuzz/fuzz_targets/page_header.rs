@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite_starter_rust::header::{DatabaseHeader, PageHeader};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = PageHeader::parse(data) {
+        let _ = header.size();
+    }
+    if let Some(header) = data.first_chunk::<100>() {
+        let _ = DatabaseHeader::parse(header);
+    }
+});
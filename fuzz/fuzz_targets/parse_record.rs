@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite_starter_rust::record::{parse_record, record_column};
+
+// Any input is either a record or an error, never a panic
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_record(data);
+    for column_index in 0..4 {
+        let _ = record_column(data, column_index);
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite_starter_rust::varint::parse_varint;
+
+fuzz_target!(|data: &[u8]| {
+    let (_, bytes_read) = parse_varint(data);
+    assert!(bytes_read <= data.len().min(9));
+});
@@ -0,0 +1,216 @@
+use crate::record::Value;
+use crate::select_sql::ArithmeticOp;
+
+/// Apply an arithmetic operator like sqlite does. NULL if either side is NULL.
+///
+/// Two integers give an integer, division truncating towards zero. When the result doesn't
+/// fit in 64 bits it is computed as a REAL instead. With a REAL on either side the result is
+/// a REAL. Dividing by zero gives NULL. `%` works on the integer parts of its operands.
+pub fn apply(op: ArithmeticOp, left: &Value, right: &Value) -> Value {
+    match (to_number(left), to_number(right)) {
+        (Some(Value::Int(left)), Some(Value::Int(right))) => integer_op(op, left, right),
+        (Some(left), Some(right)) => real_op(op, as_real(&left), as_real(&right)),
+        _ => Value::Null,
+    }
+}
+
+/// sqlite's `abs(X)`. An integer that has no positive counterpart becomes a REAL.
+pub fn abs(value: &Value) -> Value {
+    match to_number(value) {
+        Some(Value::Int(int)) => int
+            .checked_abs()
+            .map_or(Value::Float((int as f64).abs()), Value::Int),
+        Some(number) => Value::Float(as_real(&number).abs()),
+        None => Value::Null,
+    }
+}
+
+fn integer_op(op: ArithmeticOp, left: i64, right: i64) -> Value {
+    let result = match op {
+        ArithmeticOp::Add => left.checked_add(right),
+        ArithmeticOp::Subtract => left.checked_sub(right),
+        ArithmeticOp::Multiply => left.checked_mul(right),
+        ArithmeticOp::Divide | ArithmeticOp::Remainder if right == 0 => return Value::Null,
+        ArithmeticOp::Divide => left.checked_div(right),
+        // i64::MIN % -1 only overflows on the way, the remainder is 0
+        ArithmeticOp::Remainder => Some(left.checked_rem(right).unwrap_or(0)),
+    };
+    result.map_or_else(|| real_op(op, left as f64, right as f64), Value::Int)
+}
+
+fn real_op(op: ArithmeticOp, left: f64, right: f64) -> Value {
+    let result = match op {
+        ArithmeticOp::Add => left + right,
+        ArithmeticOp::Subtract => left - right,
+        ArithmeticOp::Multiply => left * right,
+        ArithmeticOp::Divide if right == 0.0 => return Value::Null,
+        ArithmeticOp::Divide => left / right,
+        ArithmeticOp::Remainder => {
+            let (left, right) = (left as i64, right as i64);
+            if right == 0 {
+                return Value::Null;
+            }
+            left.checked_rem(right).unwrap_or(0) as f64
+        }
+    };
+    if result.is_nan() {
+        Value::Null
+    } else {
+        Value::Float(result)
+    }
+}
+
+fn as_real(number: &Value) -> f64 {
+    match number {
+        Value::Int(int) => *int as f64,
+        Value::Float(float) => *float,
+        _ => 0.0,
+    }
+}
+
+/// The number a value stands for in arithmetic, None for NULL. Text and blobs are read as the
+/// longest number at their start, and 0 when they don't start with one.
+fn to_number(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Int(_) | Value::Float(_) => Some(value.clone()),
+        Value::Text(text) => Some(text_number(text)),
+        Value::Blob(blob) => Some(text_number(&String::from_utf8_lossy(blob))),
+    }
+}
+
+fn text_number(text: &str) -> Value {
+    let text = text.trim_start();
+    let bytes = text.as_bytes();
+    let digits_end = |start: usize| {
+        start
+            + bytes[start..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count()
+    };
+
+    let start = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let integer_end = digits_end(start);
+    let mut end = integer_end;
+    let mut real = false;
+    if bytes.get(end) == Some(&b'.') {
+        let fraction_end = digits_end(end + 1);
+        if integer_end > start || fraction_end > end + 1 {
+            end = fraction_end;
+            real = true;
+        }
+    }
+    if end == integer_end && integer_end == start {
+        return Value::Int(0);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = matches!(bytes.get(end + 1), Some(b'+' | b'-'));
+        let exponent_start = end + 1 + usize::from(sign);
+        let exponent_end = digits_end(exponent_start);
+        if exponent_end > exponent_start {
+            end = exponent_end;
+            real = true;
+        }
+    }
+
+    let number = &text[..end];
+    match number.parse() {
+        // An integer too large for 64 bits is read as a real
+        Ok(int) if !real => Value::Int(int),
+        _ => number.parse().map_or(Value::Int(0), Value::Float),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(int: i64) -> Value {
+        Value::Int(int)
+    }
+
+    #[test]
+    fn test_integer_division() {
+        assert_eq!(apply(ArithmeticOp::Divide, &int(7), &int(2)), int(3));
+        assert_eq!(apply(ArithmeticOp::Divide, &int(-7), &int(2)), int(-3));
+        assert_eq!(apply(ArithmeticOp::Remainder, &int(-7), &int(3)), int(-1));
+        assert_eq!(apply(ArithmeticOp::Divide, &int(1), &int(0)), Value::Null);
+        assert_eq!(
+            apply(ArithmeticOp::Remainder, &int(5), &int(0)),
+            Value::Null
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Divide, &int(i64::MIN), &int(-1)),
+            Value::Float(9.223372036854776e18)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Remainder, &int(i64::MIN), &int(-1)),
+            int(0)
+        );
+    }
+
+    #[test]
+    fn test_overflow_promotes_to_real() {
+        assert_eq!(
+            apply(ArithmeticOp::Add, &int(i64::MAX), &int(1)),
+            Value::Float(9.223372036854776e18)
+        );
+        assert_eq!(
+            apply(
+                ArithmeticOp::Multiply,
+                &int(3_000_000_000),
+                &int(4_000_000_000)
+            ),
+            Value::Float(1.2e19)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Subtract, &int(i64::MIN + 1), &int(1)),
+            int(i64::MIN)
+        );
+        assert_eq!(abs(&int(i64::MIN)), Value::Float(9.223372036854776e18));
+    }
+
+    #[test]
+    fn test_real_operands() {
+        assert_eq!(
+            apply(ArithmeticOp::Divide, &Value::Float(7.0), &int(2)),
+            Value::Float(3.5)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Remainder, &Value::Float(5.5), &int(2)),
+            Value::Float(1.0)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Divide, &Value::Float(1.5), &int(0)),
+            Value::Null
+        );
+        assert_eq!(abs(&Value::Float(-2.5)), Value::Float(2.5));
+        assert_eq!(apply(ArithmeticOp::Add, &Value::Null, &int(1)), Value::Null);
+    }
+
+    #[test]
+    fn test_text_operands() {
+        let text = |text: &str| Value::Text(text.to_owned());
+        assert_eq!(apply(ArithmeticOp::Add, &text("12abc"), &int(1)), int(13));
+        assert_eq!(apply(ArithmeticOp::Add, &text("abc"), &int(1)), int(1));
+        assert_eq!(apply(ArithmeticOp::Add, &text(" 12"), &int(0)), int(12));
+        assert_eq!(
+            apply(ArithmeticOp::Add, &text("1e3"), &int(0)),
+            Value::Float(1000.0)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Add, &text("12.0"), &int(0)),
+            Value::Float(12.0)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Add, &text(".5"), &int(0)),
+            Value::Float(0.5)
+        );
+        assert_eq!(apply(ArithmeticOp::Add, &text("-"), &int(0)), int(0));
+        assert_eq!(
+            apply(ArithmeticOp::Add, &text("99999999999999999999"), &int(0)),
+            Value::Float(1e20)
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+
+use crate::{db::DB, record::Value, select_sql::Sql};
+
+/// Query results stored column by column instead of row by row.
+#[derive(Debug, PartialEq)]
+pub struct ColumnBatch {
+    pub columns: Vec<Vec<Value>>, // one entry per selected item, each holding a value per row
+    pub num_rows: usize,
+}
+
+impl ColumnBatch {
+    /// Transpose result rows into a batch with `width` columns.
+    pub fn from_rows(width: usize, rows: Vec<Vec<Value>>) -> Self {
+        let num_rows = rows.len();
+        let mut columns = vec![Vec::with_capacity(num_rows); width];
+
+        for row in rows {
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+
+        Self { columns, num_rows }
+    }
+}
+
+impl DB {
+    /// Execute a query and return its result as columns.
+    pub fn execute_columnar(&self, query: Sql) -> Result<ColumnBatch> {
+        let result = self.execute_query(query)?;
+        Ok(ColumnBatch::from_rows(result.columns.len(), result.rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::tests::open_fixture, select_sql::parse_sql};
+
+    #[test]
+    fn test_columnar_matches_rows() {
+        let db = open_fixture("companies.db");
+        let sql = "SELECT name, country FROM companies WHERE name LIKE 'company 1_'";
+
+        let rows = db.execute_query(parse_sql(sql).unwrap()).unwrap().rows;
+        let batch = db.execute_columnar(parse_sql(sql).unwrap()).unwrap();
+
+        assert_eq!(batch.num_rows, 10);
+        assert_eq!(batch.columns.len(), 2);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(batch.columns[0][i], row[0]);
+            assert_eq!(batch.columns[1][i], row[1]);
+        }
+
+        let batch = db
+            .execute_columnar(parse_sql("SELECT name FROM companies LIMIT 0").unwrap())
+            .unwrap();
+        assert_eq!(
+            batch,
+            ColumnBatch {
+                columns: vec![vec![]],
+                num_rows: 0
+            }
+        );
+
+        // `*` is one column per table column
+        let batch = db
+            .execute_columnar(parse_sql("SELECT * FROM companies LIMIT 2").unwrap())
+            .unwrap();
+        assert_eq!(batch.columns.len(), 6);
+        assert_eq!(
+            batch.columns[1],
+            vec![
+                Value::Text("company 1".to_owned()),
+                Value::Text("company 2".to_owned())
+            ]
+        );
+    }
+}
@@ -1,32 +1,35 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, tag_no_case, take_while1},
+    bytes::complete::{tag, tag_no_case, take_while, take_while1},
     character::{
-        complete::{alphanumeric1, multispace0, multispace1},
+        complete::{multispace0, multispace1},
         is_alphanumeric,
     },
-    combinator::opt,
-    multi::{many0, many1},
-    sequence::{delimited, tuple},
+    combinator::{map, not, opt, peek, recognize},
+    error::{Error, ErrorKind},
+    multi::{many0, many1, separated_list1},
+    sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 
 #[derive(Debug)]
 pub struct IndexInfo {
-    pub index_name: String, // The name of the index
-    pub table_name: String, // the table for which index is created
-    pub column_name: String, // The column on which table is created. 
+    pub index_name: String,        // The name of the index
+    pub unique: bool,              // CREATE UNIQUE INDEX
+    pub table_name: String,        // the table for which index is created
+    pub column_names: Vec<String>, // The columns the index is on, in the order of its keys
+    pub descending: Vec<bool>,     // per column, whether `DESC` stores its keys in reverse
 }
 
-// Parse a create index sql query. 
+// Parse a create index sql query.
 pub fn parse_create_index(input: &[u8]) -> IResult<&[u8], IndexInfo> {
     let (
         remaining_input,
-        (_, _, _, _, _, index_name, _, _, _, table_name, _, _, _, column_name, _, _),
+        (_, _, unique, _, _, index_name, _, _, _, table_name, _, _, _, columns, _, _),
     ) = tuple((
         tag_no_case("create"),
         multispace1,
-        opt(tuple((tag("unique"), multispace1))),
+        opt(tuple((tag_no_case("unique"), multispace1))),
         tag_no_case("index"),
         multispace1,
         identifier,
@@ -37,17 +40,35 @@ pub fn parse_create_index(input: &[u8]) -> IResult<&[u8], IndexInfo> {
         multispace0,
         tag("("),
         multispace0,
-        identifier,
+        separated_list1(
+            tuple((multispace0, tag(","), multispace0)),
+            tuple((
+                identifier,
+                opt(preceded(
+                    multispace1,
+                    alt((tag_no_case("ASC"), tag_no_case("DESC"))),
+                )),
+            )),
+        ),
         multispace0,
         tag(")"),
     ))(input)?;
+    let (column_names, descending) = columns
+        .into_iter()
+        .map(|(column_name, order)| {
+            let descending = order.is_some_and(|order| order.eq_ignore_ascii_case(b"DESC"));
+            (column_name, descending)
+        })
+        .unzip();
 
     Ok((
         remaining_input,
         IndexInfo {
-            index_name: index_name,
+            index_name,
+            unique: unique.is_some(),
             table_name,
-            column_name,
+            column_names,
+            descending,
         },
     ))
 }
@@ -71,7 +92,10 @@ fn identifier(input: &[u8]) -> IResult<&[u8], String> {
 
 // Parse sql query for the creation of a table
 pub fn parse_creation(input: &[u8]) -> IResult<&[u8], CreateTableStatement> {
-    let (remaining_input, (_, _, _, _, _, table, _, _, _, fields, _, _, _)) = tuple((
+    let (
+        remaining_input,
+        (_, _, _, _, _, table, _, _, _, (mut fields, primary_key), _, _, without_rowid, _),
+    ) = tuple((
         tag_no_case("create"),
         multispace1,
         tag_no_case("table"),
@@ -84,50 +108,317 @@ pub fn parse_creation(input: &[u8]) -> IResult<&[u8], CreateTableStatement> {
         field_specification_list,
         multispace0,
         tag(")"),
+        opt(tuple((
+            multispace0,
+            tag_no_case("WITHOUT"),
+            multispace1,
+            tag_no_case("ROWID"),
+        ))),
         opt(tag(";")),
     ))(input)?;
 
-    Ok((remaining_input, CreateTableStatement { table, fields }))
+    // Without a rowid there is nothing for an INTEGER PRIMARY KEY to be an alias of
+    let without_rowid = without_rowid.is_some();
+    if without_rowid {
+        for field in fields.iter_mut() {
+            field.is_primary_key = false;
+        }
+    }
+
+    Ok((
+        remaining_input,
+        CreateTableStatement {
+            table,
+            fields,
+            primary_key,
+            without_rowid,
+        },
+    ))
 }
 
 fn is_sql_identifier(chr: u8) -> bool {
     is_alphanumeric(chr) || chr == b'_'
 }
 
-fn field_specification_list(input: &[u8]) -> IResult<&[u8], Vec<Field>> {
-    many1(field_specification)(input)
+// Columns come first, table constraints like `PRIMARY KEY(a)` can only follow them.
+// Returns the fields along with the columns of the primary key.
+fn field_specification_list(input: &[u8]) -> IResult<&[u8], (Vec<Field>, Vec<String>)> {
+    let (input, (columns, constraints)) =
+        tuple((many1(field_specification), many0(table_constraint)))(input)?;
+    let (mut fields, primary_key_flags): (Vec<Field>, Vec<bool>) = columns.into_iter().unzip();
+    let mut primary_key: Vec<String> = fields
+        .iter()
+        .zip(primary_key_flags)
+        .filter(|(_, primary_key)| *primary_key)
+        .map(|(field, _)| field.name.clone())
+        .collect();
+
+    for columns in constraints.into_iter().flatten() {
+        // A single column primary key declared as a table constraint works like a column one
+        if let [column] = columns.as_slice() {
+            for field in fields
+                .iter_mut()
+                .filter(|field| field.name.eq_ignore_ascii_case(column))
+            {
+                field.is_primary_key = is_rowid_type(field.data_type.as_deref());
+            }
+        }
+        primary_key = columns;
+    }
+
+    Ok((input, (fields, primary_key)))
 }
 
-fn field_specification(input: &[u8]) -> IResult<&[u8], Field> {
-    let (remaining_input, (column, _, constraints, _)) = tuple((
+// Parse a column definition. Returns the field and whether it is declared PRIMARY KEY.
+fn field_specification(input: &[u8]) -> IResult<&[u8], (Field, bool)> {
+    let (remaining_input, (_, column, data_type, constraints, _)) = tuple((
+        not(table_constraint_start),
         identifier,
-        opt(delimited(multispace0, alphanumeric1, multispace0)), // type
+        opt(delimited(multispace0, type_name, multispace0)),
         many0(column_constraint),
         opt(delimited(multispace0, tag(","), multispace0)),
     ))(input)?;
 
-    Ok((remaining_input, Field { name: column, is_primary_key: constraints.contains(&"PRIMARY KEY".to_string()) }))
+    let default_value = constraints.iter().find_map(|constraint| match constraint {
+        ColumnConstraint::Default(value) => Some(value.clone()),
+        _ => None,
+    });
+    let primary_key = constraints.contains(&ColumnConstraint::PrimaryKey);
+    let is_primary_key = primary_key && is_rowid_type(data_type.as_deref());
+
+    Ok((
+        remaining_input,
+        (
+            Field {
+                name: column,
+                data_type,
+                is_primary_key,
+                not_null: constraints.contains(&ColumnConstraint::NotNull),
+                default_value,
+            },
+            primary_key,
+        ),
+    ))
+}
+
+// Only a primary key declared with exactly the type INTEGER is an alias of the rowid. With
+// any other type, even INT, it is an ordinary column stored in the record.
+fn is_rowid_type(data_type: Option<&str>) -> bool {
+    data_type.is_some_and(|data_type| data_type.eq_ignore_ascii_case("INTEGER"))
+}
+
+// Keywords that start a column constraint, which ends the type name
+const CONSTRAINT_KEYWORDS: [&str; 12] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+    "AUTOINCREMENT",
+];
+
+// match a declared type as written, which can be several words with a size after them,
+// e.g. `integer`, `unsigned big int` or `decimal(10, 2)`
+fn type_name(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, type_name) = recognize(tuple((
+        separated_list1(multispace1, type_word),
+        opt(preceded(
+            multispace0,
+            delimited(tag("("), take_while(|ch| ch != b')'), tag(")")),
+        )),
+    )))(input)?;
+
+    Ok((input, String::from_utf8_lossy(type_name).into_owned()))
 }
 
-fn column_constraint(input: &[u8]) -> IResult<&[u8], String> {
-    let not_null = delimited(multispace0, tag_no_case("NOT NULL"), multispace0);
+fn type_word(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (remaining_input, word) = take_while1(is_sql_identifier)(input)?;
+    if CONSTRAINT_KEYWORDS
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword.as_bytes()))
+    {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::Tag)));
+    }
+    Ok((remaining_input, word))
+}
 
-    let auto_increment = delimited(multispace0, tag_no_case("AUTOINCREMENT"), multispace0);
+// The keyword a table constraint starts with. Requiring whitespace or `(` after it keeps
+// columns like `unique_id` from being mistaken for one.
+fn table_constraint_start(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    terminated(
+        alt((
+            tag_no_case("CONSTRAINT"),
+            tag_no_case("PRIMARY"),
+            tag_no_case("FOREIGN"),
+            tag_no_case("UNIQUE"),
+            tag_no_case("CHECK"),
+        )),
+        peek(alt((multispace1, tag("(")))),
+    )(input)
+}
 
-    let primary_key = delimited(multispace0, tag_no_case("PRIMARY KEY"), multispace0);
+// Parse a table constraint. Returns the columns of a PRIMARY KEY constraint and None for
+// any other kind of constraint, which are skipped.
+fn table_constraint(input: &[u8]) -> IResult<&[u8], Option<Vec<String>>> {
+    let primary_key = map(
+        tuple((
+            opt(tuple((
+                tag_no_case("CONSTRAINT"),
+                multispace1,
+                identifier,
+                multispace1,
+            ))),
+            tag_no_case("PRIMARY"),
+            multispace1,
+            tag_no_case("KEY"),
+            multispace0,
+            delimited(
+                tag("("),
+                separated_list1(
+                    tag(","),
+                    delimited(
+                        multispace0,
+                        terminated(
+                            identifier,
+                            opt(tuple((
+                                multispace1,
+                                alt((tag_no_case("ASC"), tag_no_case("DESC"))),
+                            ))),
+                        ),
+                        multispace0,
+                    ),
+                ),
+                tag(")"),
+            ),
+        )),
+        |(_, _, _, _, _, columns)| Some(columns),
+    );
+    let other = map(table_constraint_start, |_| None);
 
-    let (input, constraint) = alt((not_null, auto_increment, primary_key))(input)?;
+    let (input, (_, constraint, _, _)) = tuple((
+        multispace0,
+        alt((primary_key, other)),
+        until_list_end, // e.g. ON CONFLICT or the column list and REFERENCES of a foreign key
+        opt(delimited(multispace0, tag(","), multispace0)),
+    ))(input)?;
 
-    Ok((input, String::from_utf8(constraint.into()).unwrap().to_ascii_uppercase()))
+    Ok((input, constraint))
 }
 
+// Consume text up to the next `,` or `)` that isn't nested in parentheses or quotes
+fn until_list_end(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, &ch) in input.iter().enumerate() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"') => quote = Some(ch),
+            (None, b'(') => depth += 1,
+            (None, b',' | b')') if depth == 0 => return Ok((&input[i..], &input[..i])),
+            (None, b')') => depth -= 1,
+            _ => {}
+        }
+    }
 
+    Err(nom::Err::Error(Error::new(input, ErrorKind::TakeUntil)))
+}
 
 #[derive(Debug, PartialEq)]
+enum ColumnConstraint {
+    NotNull,
+    AutoIncrement,
+    PrimaryKey,
+    Default(String), // the default value as written, e.g. `'x'` or `5`
+}
+
+fn column_constraint(input: &[u8]) -> IResult<&[u8], ColumnConstraint> {
+    let not_null = map(tag_no_case("NOT NULL"), |_| ColumnConstraint::NotNull);
+
+    let auto_increment = map(tag_no_case("AUTOINCREMENT"), |_| {
+        ColumnConstraint::AutoIncrement
+    });
+
+    let primary_key = map(tag_no_case("PRIMARY KEY"), |_| ColumnConstraint::PrimaryKey);
+
+    let default = map(
+        preceded(
+            tuple((tag_no_case("DEFAULT"), multispace1)),
+            default_literal,
+        ),
+        ColumnConstraint::Default,
+    );
+
+    delimited(
+        multispace0,
+        alt((not_null, auto_increment, primary_key, default)),
+        multispace0,
+    )(input)
+}
+
+// match a literal default value. A quoted string (quotes included) or a bare number/word
+fn default_literal(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, literal) = alt((
+        recognize(delimited(tag("'"), take_while(|ch| ch != b'\''), tag("'"))),
+        take_while1(|ch: u8| is_sql_identifier(ch) || ch == b'-' || ch == b'.'),
+    ))(input)?;
+
+    Ok((input, String::from_utf8_lossy(literal).into_owned()))
+}
+
+#[derive(Debug, PartialEq, Clone)]
 
 pub struct Field {
     pub name: String,
-    pub is_primary_key: bool,
+    pub data_type: Option<String>, // declared type as written. None when the column has no type
+    pub is_primary_key: bool,      // INTEGER PRIMARY KEY, the column is an alias of the rowid
+    pub not_null: bool,
+    pub default_value: Option<String>, // DEFAULT value as written in the CREATE statement
+}
+
+/// The kind of values a column prefers, which decides how values compared with it are
+/// converted first.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob, // no preference, values are compared as they are
+    Real,
+    Numeric,
+}
+
+impl Field {
+    /// The affinity of the column by sqlite's rules for the declared type, the first that
+    /// applies: INT is INTEGER, CHAR, CLOB or TEXT is TEXT, BLOB or no type is BLOB, REAL,
+    /// FLOA or DOUB is REAL, and anything else NUMERIC.
+    pub fn affinity(&self) -> Affinity {
+        let Some(data_type) = &self.data_type else {
+            return Affinity::Blob;
+        };
+        let data_type = data_type.to_ascii_uppercase();
+        let contains = |words: &[&str]| words.iter().any(|word| data_type.contains(word));
+        if contains(&["INT"]) {
+            Affinity::Integer
+        } else if contains(&["CHAR", "CLOB", "TEXT"]) {
+            Affinity::Text
+        } else if contains(&["BLOB"]) {
+            Affinity::Blob
+        } else if contains(&["REAL", "FLOA", "DOUB"]) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    pub fn has_real_affinity(&self) -> bool {
+        self.affinity() == Affinity::Real
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -136,6 +427,10 @@ pub struct CreateTableStatement {
     pub table: String,
 
     pub fields: Vec<Field>,
+
+    pub primary_key: Vec<String>, // the columns of the PRIMARY KEY in key order, if it has one
+
+    pub without_rowid: bool, // WITHOUT ROWID, the rows are stored in an index btree by primary key
 }
 
 #[cfg(test)]
@@ -145,12 +440,201 @@ mod tests {
     #[test]
     fn test_simple() {
         let statement = "CREATE TABLE companies\n(\n\tid integer primary key autoincrement\n, name text, domain text, year_founded text, industry text, \"size range\" text, locality text, country text, current_employees text, total_employees text)";
-        let resp = parse_creation(statement.as_bytes()).unwrap();
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert_eq!(resp.table, "companies");
+        assert_eq!(resp.fields.len(), 10);
+        assert!(resp.fields[0].is_primary_key);
+        assert_eq!(resp.fields[5].name, "size range");
+    }
+
+    #[test]
+    fn test_integer_primary_key() {
+        let statement = "CREATE TABLE t (id integer primary key autoincrement, name text)";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert!(resp.fields[0].is_primary_key);
+        assert!(!resp.fields[1].is_primary_key);
+
+        for statement in [
+            "CREATE TABLE t (code text primary key, name text)",
+            "CREATE TABLE t (n int primary key, name text)",
+            "CREATE TABLE t (n primary key, name text)",
+            "CREATE TABLE t (n, name text, PRIMARY KEY (n))",
+        ] {
+            let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+            assert!(!resp.fields[0].is_primary_key, "{}", statement);
+        }
+
+        let statement = "CREATE TABLE t (name text, n INTEGER, PRIMARY KEY (n))";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert!(resp.fields[1].is_primary_key);
+    }
+
+    #[test]
+    fn test_column_constraints() {
+        let statement = "CREATE TABLE t (a integer NOT NULL DEFAULT 5, b text DEFAULT 'x y', c)";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert_eq!(
+            resp.fields,
+            vec![
+                Field {
+                    name: "a".to_owned(),
+                    data_type: Some("integer".to_owned()),
+                    is_primary_key: false,
+                    not_null: true,
+                    default_value: Some("5".to_owned()),
+                },
+                Field {
+                    name: "b".to_owned(),
+                    data_type: Some("text".to_owned()),
+                    is_primary_key: false,
+                    not_null: false,
+                    default_value: Some("'x y'".to_owned()),
+                },
+                Field {
+                    name: "c".to_owned(),
+                    data_type: None,
+                    is_primary_key: false,
+                    not_null: false,
+                    default_value: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_types() {
+        let statement = "CREATE TABLE t (id integer primary key, name text, price decimal(10, 2), flags unsigned big int not null, misc, k PRIMARY KEY)";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        let types: Vec<(&str, Option<&str>)> = resp
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.data_type.as_deref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("id", Some("integer")),
+                ("name", Some("text")),
+                ("price", Some("decimal(10, 2)")),
+                ("flags", Some("unsigned big int")),
+                ("misc", None),
+                ("k", None),
+            ]
+        );
+        assert!(resp.fields[0].is_primary_key);
+        assert!(resp.fields[3].not_null);
+        // Without the INTEGER type the key isn't the rowid
+        assert!(!resp.fields[5].is_primary_key);
+    }
+
+    #[test]
+    fn test_table_constraints() {
+        let statement = "CREATE TABLE t (a integer, b, unique_id, PRIMARY KEY(a), FOREIGN KEY (b) REFERENCES u(c), CHECK (b != ')'))";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        let names: Vec<&str> = resp
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "b", "unique_id"]);
+        assert!(resp.fields[0].is_primary_key);
+        assert!(!resp.fields[1].is_primary_key);
+
+        // Only a single column key aliases the rowid
+        let statement = "CREATE TABLE t (a integer, b text, CONSTRAINT pk PRIMARY KEY (a DESC, b))";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert_eq!(resp.fields.len(), 2);
+        assert!(resp.fields.iter().all(|field| !field.is_primary_key));
+        assert_eq!(resp.primary_key, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_without_rowid() {
+        let statement = "CREATE TABLE t (a text, b integer, PRIMARY KEY (b, a)) WITHOUT ROWID";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert!(resp.without_rowid);
+        assert_eq!(resp.primary_key, vec!["b", "a"]);
+
+        // There is no rowid for the INTEGER PRIMARY KEY to alias
+        let statement = "CREATE TABLE t (id integer primary key, name text)\n without rowid";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert!(resp.without_rowid);
+        assert_eq!(resp.primary_key, vec!["id"]);
+        assert!(!resp.fields[0].is_primary_key);
+
+        let statement = "CREATE TABLE t (id integer primary key, name text)";
+        let (_, resp) = parse_creation(statement.as_bytes()).unwrap();
+        assert!(!resp.without_rowid);
+    }
+
+    #[test]
+    fn test_real_affinity() {
+        let field = |data_type: Option<&str>| Field {
+            name: "a".to_owned(),
+            data_type: data_type.map(str::to_owned),
+            is_primary_key: false,
+            not_null: false,
+            default_value: None,
+        };
+        for data_type in ["REAL", "double precision", "Float", "DOUBLE"] {
+            assert!(field(Some(data_type)).has_real_affinity(), "{}", data_type);
+        }
+        // INT and the text types win over REAL
+        for data_type in ["integer", "FLOATING POINT", "REAL TEXT", "numeric", "blob"] {
+            assert!(!field(Some(data_type)).has_real_affinity(), "{}", data_type);
+        }
+        assert!(!field(None).has_real_affinity());
+
+        let cases = [
+            (Some("INTEGER"), Affinity::Integer),
+            (Some("tinyint"), Affinity::Integer),
+            (Some("varchar(20)"), Affinity::Text),
+            (Some("blob"), Affinity::Blob),
+            (None, Affinity::Blob),
+            (Some("DOUBLE PRECISION"), Affinity::Real),
+            (Some("decimal(10,5)"), Affinity::Numeric),
+            (Some("boolean"), Affinity::Numeric),
+        ];
+        for (data_type, affinity) in cases {
+            assert_eq!(field(data_type).affinity(), affinity, "{:?}", data_type);
+        }
     }
 
     #[test]
     fn test_parse_create_index() {
         let statement = "CREATE INDEX idx_companies_country\n\ton companies (country)";
-        let resp = parse_create_index(statement.as_bytes()).unwrap();
+        let (_, resp) = parse_create_index(statement.as_bytes()).unwrap();
+        assert_eq!(resp.index_name, "idx_companies_country");
+        assert!(!resp.unique);
+        assert_eq!(resp.table_name, "companies");
+        assert_eq!(resp.column_names, vec!["country"]);
+    }
+
+    #[test]
+    fn test_parse_create_unique_index() {
+        let statement = "CREATE UNIQUE INDEX idx_t_a ON t (a)";
+        let (_, resp) = parse_create_index(statement.as_bytes()).unwrap();
+        assert_eq!(resp.index_name, "idx_t_a");
+        assert!(resp.unique);
+    }
+
+    #[test]
+    fn test_parse_create_index_order() {
+        let statement = "CREATE INDEX idx_t_a ON t (a DESC)";
+        let (_, resp) = parse_create_index(statement.as_bytes()).unwrap();
+        assert_eq!(resp.column_names, vec!["a"]);
+        assert_eq!(resp.descending, vec![true]);
+
+        let statement = "CREATE INDEX idx_t_a ON t (a asc)";
+        let (_, resp) = parse_create_index(statement.as_bytes()).unwrap();
+        assert_eq!(resp.descending, vec![false]);
+    }
+
+    #[test]
+    fn test_parse_composite_index() {
+        let statement = "CREATE INDEX idx_t_abc ON t (a, b DESC ,\"c d\" ASC)";
+        let (_, resp) = parse_create_index(statement.as_bytes()).unwrap();
+        assert_eq!(resp.column_names, vec!["a", "b", "c d"]);
+        assert_eq!(resp.descending, vec![false, true, false]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,291 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// sqlite's `date(timevalue, modifier, ...)`: the date as `YYYY-MM-DD`.
+/// None (NULL) if an argument is NULL or can't be understood.
+pub fn date(arguments: &[Option<String>]) -> Option<String> {
+    evaluate(arguments).map(format_date)
+}
+
+/// sqlite's `datetime(timevalue, modifier, ...)`: the time as `YYYY-MM-DD HH:MM:SS`.
+pub fn datetime(arguments: &[Option<String>]) -> Option<String> {
+    let time = evaluate(arguments)?;
+    let seconds = time.rem_euclid(MILLIS_PER_DAY) / 1000;
+    Some(format!(
+        "{} {:02}:{:02}:{:02}",
+        format_date(time),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    ))
+}
+
+fn format_date(time: i64) -> String {
+    let (year, month, day) = civil_from_days(time.div_euclid(MILLIS_PER_DAY));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The time in milliseconds since 1970-01-01 00:00:00 UTC, after applying the modifiers.
+///
+/// The time value is ISO-8601 text (`YYYY-MM-DD`, optionally followed by `HH:MM`, `HH:MM:SS`
+/// or `HH:MM:SS.SSS` after a space or `T`), `now`, or a number. A number is a Julian day
+/// number, or seconds since 1970 when the first modifier is `unixepoch`. The other modifiers
+/// are `±N days`, `hours`, `minutes` and `seconds`, and `±N months` and `years` for a whole N.
+/// Without arguments the time is now.
+fn evaluate(arguments: &[Option<String>]) -> Option<i64> {
+    let (value, modifiers) = match arguments.split_first() {
+        Some((value, modifiers)) => (value.as_deref()?.trim(), modifiers),
+        None => ("now", arguments),
+    };
+    let mut modifiers = modifiers.iter().map(|modifier| {
+        modifier
+            .as_deref()
+            .map(|modifier| modifier.trim().to_ascii_lowercase())
+    });
+
+    let mut time = match value.parse::<f64>() {
+        Ok(number) if !number.is_finite() => return None,
+        Ok(seconds) if arguments.get(1).is_some_and(is_unixepoch) => {
+            modifiers.next();
+            (seconds * 1000.0).round() as i64
+        }
+        // Julian day 2440587.5 is 1970-01-01 00:00:00
+        Ok(julian_day) => ((julian_day - 2_440_587.5) * MILLIS_PER_DAY as f64).round() as i64,
+        Err(_) if value.eq_ignore_ascii_case("now") => now(),
+        Err(_) => parse_iso8601(value)?,
+    };
+    for modifier in modifiers {
+        time = apply_modifier(time, &modifier?)?;
+    }
+
+    // sqlite only handles years 0000 to 9999
+    let (year, _, _) = civil_from_days(time.div_euclid(MILLIS_PER_DAY));
+    (0..=9999).contains(&year).then_some(time)
+}
+
+fn is_unixepoch(modifier: &Option<String>) -> bool {
+    modifier
+        .as_deref()
+        .is_some_and(|modifier| modifier.trim().eq_ignore_ascii_case("unixepoch"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
+
+/// Parse `YYYY-MM-DD[( |T)HH:MM[:SS[.SSS]]]`.
+fn parse_iso8601(value: &str) -> Option<i64> {
+    let (date, time) = match value.find([' ', 'T']) {
+        Some(separator) => (
+            &value[..separator],
+            Some(value[separator + 1..].trim_start()),
+        ),
+        None => (value, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year = fixed_digits(parts.next()?, 4)?;
+    let month = fixed_digits(parts.next()?, 2)?;
+    let day = fixed_digits(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day)?;
+
+    let millis = match time {
+        None => 0,
+        Some(time) => {
+            let mut parts = time.splitn(3, ':');
+            let hours = fixed_digits(parts.next()?, 2)?;
+            let minutes = fixed_digits(parts.next()?, 2)?;
+            let seconds = match parts.next() {
+                Some(seconds) if seconds.len() >= 2 && seconds.as_bytes()[0].is_ascii_digit() => {
+                    seconds.parse::<f64>().ok()?
+                }
+                Some(_) => return None,
+                None => 0.0,
+            };
+            if hours > 23 || minutes > 59 || !(0.0..60.0).contains(&seconds) {
+                return None;
+            }
+            (hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as i64
+        }
+    };
+
+    Some(days * MILLIS_PER_DAY + millis)
+}
+
+/// A number written with exactly `width` digits.
+fn fixed_digits(text: &str, width: usize) -> Option<i64> {
+    if text.len() != width || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Apply a `±N unit` modifier to a time. None if the time moves out of range.
+fn apply_modifier(time: i64, modifier: &str) -> Option<i64> {
+    let (amount, unit) = modifier.split_once(char::is_whitespace)?;
+    let amount = amount
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| amount.is_finite())?;
+    let unit = unit.trim_start();
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+
+    let millis_per_unit = match unit {
+        "day" => MILLIS_PER_DAY,
+        "hour" => 3_600_000,
+        "minute" => 60_000,
+        "second" => 1000,
+        "month" | "year" if amount.fract() == 0.0 => {
+            let months = (amount as i64).checked_mul(if unit == "year" { 12 } else { 1 })?;
+            return add_months(time, months);
+        }
+        _ => return None,
+    };
+    // Casting saturates, a change that doesn't fit is out of range
+    let change = (amount * millis_per_unit as f64).round();
+    if change.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    time.checked_add(change as i64)
+}
+
+/// Move a time by whole months, keeping the day of the month. A day past the end of the
+/// new month rolls over, so a month after January 31st is early March.
+fn add_months(time: i64, months: i64) -> Option<i64> {
+    let days = time.div_euclid(MILLIS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let month_index = (year * 12 + month - 1).checked_add(months)?;
+    let days_moved = days_from_civil(
+        month_index.div_euclid(12),
+        month_index.rem_euclid(12) + 1,
+        day,
+    )?;
+    time.checked_add((days_moved - days).checked_mul(MILLIS_PER_DAY)?)
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+/// Days past the end of the month count on into the next one. None if the year is too far
+/// out for the number of days to fit.
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)
+}
+
+/// The year, month and day of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<Option<String>> {
+        values.iter().map(|value| Some(value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_unix_timestamp() {
+        assert_eq!(
+            datetime(&args(&["1700000000", "unixepoch"])),
+            Some("2023-11-14 22:13:20".to_owned())
+        );
+        assert_eq!(
+            date(&args(&["1700000000", "unixepoch"])),
+            Some("2023-11-14".to_owned())
+        );
+        assert_eq!(
+            datetime(&args(&["-1", "unixepoch"])),
+            Some("1969-12-31 23:59:59".to_owned())
+        );
+        // Without unixepoch a number is a Julian day number
+        assert_eq!(
+            datetime(&args(&["2460000.25"])),
+            Some("2023-02-24 18:00:00".to_owned())
+        );
+        // unixepoch has to come right after the time value
+        assert_eq!(date(&args(&["0", "+1 day", "unixepoch"])), None);
+    }
+
+    #[test]
+    fn test_modifiers() {
+        assert_eq!(
+            date(&args(&["2024-02-28", "+1 day"])),
+            Some("2024-02-29".to_owned())
+        );
+        assert_eq!(
+            datetime(&args(&["2023-12-31 23:30:00", "+1 day", "+45 minutes"])),
+            Some("2024-01-02 00:15:00".to_owned())
+        );
+        assert_eq!(
+            datetime(&args(&["2024-01-01T00:00", "-1.5 days", "+30 seconds"])),
+            Some("2023-12-30 12:00:30".to_owned())
+        );
+        assert_eq!(
+            date(&args(&["2021-01-31", "+1 month"])),
+            Some("2021-03-03".to_owned())
+        );
+        assert_eq!(
+            date(&args(&["2020-02-29", "-1 year"])),
+            Some("2019-03-01".to_owned())
+        );
+        assert_eq!(date(&args(&["2024-01-01", "+1 fortnight"])), None);
+
+        // Changes too large for a time are NULL
+        for modifier in [
+            "+9223372036854775807 days",
+            "-99999999999 years",
+            "+768614336404564650 months",
+            "+1e300 seconds",
+        ] {
+            assert_eq!(date(&args(&["2024-01-01", modifier])), None, "{}", modifier);
+        }
+    }
+
+    #[test]
+    fn test_iso8601_text() {
+        assert_eq!(
+            datetime(&args(&["2024-03-05"])),
+            Some("2024-03-05 00:00:00".to_owned())
+        );
+        assert_eq!(
+            datetime(&args(&["2024-03-05 07:08:09.6"])),
+            Some("2024-03-05 07:08:09".to_owned())
+        );
+        for invalid in [
+            "2024-3-05",
+            "2024-13-01",
+            "2024-03-05 25:00",
+            "yesterday",
+            "",
+        ] {
+            assert_eq!(date(&args(&[invalid])), None, "{}", invalid);
+        }
+        assert_eq!(date(&[None]), None);
+        assert_eq!(date(&args(&["9999-12-31", "+1 day"])), None);
+    }
+}
@@ -1,19 +1,33 @@
 use std::{
-    collections::HashMap,
-    fs::File,
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::{self, File},
+    ops::Bound,
+    os::unix::fs::FileExt,
+    path::Path,
     vec,
 };
 
 use crate::{
-    creation_sql::{parse_create_index, parse_creation, Field, IndexInfo},
-    header::{BTreePage, PageHeader},
-    record::parse_record,
+    arithmetic,
+    creation_sql::{
+        parse_create_index, parse_creation, Affinity, CreateTableStatement, Field, IndexInfo,
+    },
+    datetime,
+    header::{BTreePage, DatabaseHeader, PageHeader},
+    json, overflow, pattern, printf,
+    ptrmap::{self, PtrmapEntry},
+    record::{parse_record, Value, ValueKey},
     schema::Schema,
-    select_sql::{SelectClause, Sql},
+    select_sql::{
+        CompareOp, FunctionCall, Join, Operand, Pragma, ScalarCall, SelectItem, Sql, Statement,
+        WhereCondition, WhereExpr, SCALAR_FUNCTIONS,
+    },
     util,
     varint::parse_varint,
 };
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 
 // Ideally return size here as well
@@ -40,435 +54,4473 @@ pub fn parse_cell_pointers(stream: &[u8], number_of_cells: u16) -> Vec<u16> {
         .collect::<Vec<_>>()
 }
 
-pub fn parse_schemas(database: &[u8], number_of_cells: u16) -> Result<Vec<Schema>> {
-    let cell_pointers = parse_cell_pointers(&database[108..], number_of_cells);
+/// The cell a cell pointer points to, up to the end of the page.
+fn cell_at(page: &[u8], cell_pointer: u16) -> Result<&[u8]> {
+    page.get(cell_pointer as usize..)
+        .ok_or_else(|| anyhow!("Cell pointer {} is past the end of the page", cell_pointer))
+}
 
-    // Obtain all records
-    let schemas: Result<Vec<Schema>> = cell_pointers
-        .into_iter()
-        .map(|cell_pointer| {
-            let record = parse_btree_leaf_cell_content(cell_pointer, database)?;
-            let record = Schema::parse(record)?;
-            Ok(record)
-        })
-        .collect();
+/// The page number of the left child an interior page cell starts with.
+fn left_child_pointer(cell: &[u8]) -> Result<usize> {
+    let Some(bytes) = cell.get(..4) else {
+        bail!("Interior page cell is truncated");
+    };
+    Ok(u32::from_be_bytes(bytes.try_into()?) as usize)
+}
+
+/// Record that a btree walk reached a page. Every page has a single parent, so reaching one
+/// twice means the file is corrupt and the btree has a cycle, which would be walked forever.
+fn visit_page(visited: &mut HashSet<usize>, page_number: usize) -> Result<()> {
+    if !visited.insert(page_number) {
+        bail!(
+            "Page {} is reached twice, the btree has a cycle",
+            page_number
+        );
+    }
+    Ok(())
+}
+
+/// Read the schema table. It is the table btree rooted at page 1, which spans more than one
+/// page once the database has enough tables. `usable_size` is the page size less the bytes
+/// reserved at the end of each page.
+pub fn read_schemas(file: &File, page_size: u16, usable_size: usize) -> Result<Vec<Schema>> {
+    let mut schemas = vec![];
+    read_schema_page(
+        file,
+        (page_size, usable_size),
+        1,
+        &mut schemas,
+        &mut HashSet::new(),
+    )?;
+    Ok(schemas)
+}
+
+fn read_schema_page(
+    file: &File,
+    (page_size, usable_size): (u16, usize),
+    page_number: usize,
+    schemas: &mut Vec<Schema>,
+    visited: &mut HashSet<usize>,
+) -> Result<()> {
+    visit_page(visited, page_number)?;
+    let page = util::read_page(file, page_size, page_number)?;
+
+    // On first page first 100 bytes are database header
+    let offset = if page_number == 1 { 100 } else { 0 };
+    let page_header = get_page_header(&page[offset..])?;
+    let cell_pointers = parse_cell_pointers(
+        &page[offset + page_header.size()..],
+        page_header.number_of_cells,
+    );
+
+    match page_header.page_type {
+        BTreePage::LeafTable => {
+            for cell_pointer in cell_pointers {
+                let record = parse_btree_leaf_cell_content(
+                    cell_pointer,
+                    &page,
+                    usable_size,
+                    |page_number| util::read_page(file, page_size, page_number),
+                )?;
+                schemas.push(Schema::parse(record)?);
+            }
+        }
+        BTreePage::InteriorTable => {
+            // Each cell starts with the page number of its left child
+            for cell_pointer in cell_pointers {
+                let child = left_child_pointer(cell_at(&page, cell_pointer)?)?;
+                read_schema_page(file, (page_size, usable_size), child, schemas, visited)?;
+            }
+            if let Some(right_most_pointer) = page_header.right_most_pointer {
+                let right_most_pointer = right_most_pointer as usize;
+                read_schema_page(
+                    file,
+                    (page_size, usable_size),
+                    right_most_pointer,
+                    schemas,
+                    visited,
+                )?;
+            }
+        }
+        page_type => bail!(
+            "Schema page {} should be a table btree page, found {:?}",
+            page_number,
+            page_type
+        ),
+    }
 
-    schemas
+    Ok(())
 }
 
+/// Parse the record of a table leaf cell. A payload too large for the page continues on
+/// overflow pages, which are fetched with `read_page`.
 pub fn parse_btree_leaf_cell_content(
     cell_pointer: u16,
     page_stream: &[u8],
-) -> Result<Vec<Vec<u8>>> {
-    let stream = &page_stream[cell_pointer as usize..];
-    let (_payload_size, offset) = parse_varint(stream); // total number of bytes of payload
+    usable_size: usize,
+    read_page: impl FnMut(usize) -> Result<Vec<u8>>,
+) -> Result<Vec<Value>> {
+    let stream = cell_at(page_stream, cell_pointer)?;
+    let (payload_size, offset) = parse_varint(stream); // total number of bytes of payload
     let (_rowid, read_bytes) = parse_varint(&stream[offset..]); // integer key (rowid).
 
     // Now the actual content start
-    let record = parse_record(&stream[offset + read_bytes..]);
-
-    record
+    let local_size = overflow::table_local_payload_size(payload_size, usable_size);
+    let payload = overflow::read_payload(
+        &stream[offset + read_bytes..],
+        payload_size,
+        local_size,
+        usable_size,
+        read_page,
+    )?;
+    parse_record(&payload)
 }
 
-pub fn get_page_size(database: &Vec<u8>) -> Result<u16> {
-    let page_size = u16::from_be_bytes(TryInto::<[u8; 2]>::try_into(&database[16..18]).unwrap());
-    return Ok(page_size);
-}
-
-fn parse_index_payload(stream: &[u8]) -> Result<Vec<Vec<u8>>> {
-    let (_payload_size, payload_size_bytes) = parse_varint(stream);
-    let key_record = parse_record(&stream[payload_size_bytes..])?;
-    Ok(key_record)
+/// The rowid of an index record, which is stored after the indexed columns.
+fn index_row_id(record: &[Value]) -> Result<usize> {
+    match record.last() {
+        Some(Value::Int(row_id)) => Ok(*row_id as usize),
+        _ => bail!("Index record without a rowid"),
+    }
 }
 
-pub fn parse_24bit_be_twos_complement(bytes: &[u8]) -> i64 {
-    match &bytes.len() {
-        3 => i32::from_be_bytes([
-            if bytes[0] & 0x80 != 0 { 0xff } else { 0 },
-            bytes[0],
-            bytes[1],
-            bytes[2],
-        ]) as i64,
-        2 => i16::from_be_bytes([bytes[0], bytes[1]]) as i64,
-        1 => bytes[0] as i64,
-        _ => panic!("SHOULDNT BE HERE"),
-    }
+/// Skip the first `offset` items and keep at most `limit` of the rest.
+fn apply_window<T>(items: Vec<T>, limit: Option<usize>, offset: Option<usize>) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
 }
 
-pub struct DB {
-    pub page_size: u16,
-    pub schemas: Vec<Schema>,
-    pub file: File,
+/// Whether every matching record becomes exactly one row in scan order, i.e. there is no
+/// aggregation, DISTINCT or sorting. OFFSET and LIMIT can then be applied to the records (or
+/// row ids) before the rows are built.
+fn window_records(query: &Sql, fields: &HashMap<String, (usize, Field)>) -> bool {
+    // Records come in rowid order, so ordering by the rowid ascending needs no sorting
+    let scan_ordered = match query.order_by.as_slice() {
+        [] => true,
+        [(SelectItem::Column(column), false)] => is_rowid(column, fields),
+        _ => false,
+    };
+    !query.is_aggregate() && !query.distinct && scan_ordered
 }
 
-pub struct Record {
-    pub row_id: String,
-    pub columns: Vec<String>,
+/// The values that required equality conditions give the columns, up to the first column
+/// without one.
+fn equality_prefix(columns: &[String], conditions: &[&WhereCondition]) -> Vec<Value> {
+    columns
+        .iter()
+        .map_while(|column| {
+            conditions.iter().find_map(|condition| match condition {
+                WhereCondition::Equals(name, value) if name.eq_ignore_ascii_case(column) => {
+                    Some(value.clone())
+                }
+                _ => None,
+            })
+        })
+        .collect()
 }
 
-impl DB {
-    /// Create a new instance of the DB.
-    pub fn new(page_size: u16, schemas: Vec<Schema>, file: File) -> Self {
-        Self {
-            page_size,
-            schemas,
-            file,
-        }
+/// Whether the query only needs the indexed columns and the rowid, so it can be answered from
+/// the index without reading the table. Queries without select items, like the per table
+/// queries of a join, need the whole record.
+fn covered_by_index(
+    query: &Sql,
+    index_columns: &[String],
+    fields: &HashMap<String, (usize, Field)>,
+) -> bool {
+    if query.select_clause.is_empty() {
+        return false;
     }
-
-    /// Process a query
-    /// Tries to use index if possible.
-    /// Else does a full scan.
-    /// Only supports single equality check in where clause for now.
-    pub fn process_query(&self, query: Sql) -> Result<()> {
-        // Store whether IndexInfo if you can use one for the query
-        let mut idx_info: Option<IndexInfo> = None;
-
-        // If there is a where clause. See if you can use the index.
-        if let Some((key, _)) = query.where_clause.clone() {
-            // See if you can find a index;
-            let index_schema = self
-                .schemas
+    query.referenced_columns().is_some_and(|columns| {
+        columns.into_iter().all(|name| {
+            index_columns
                 .iter()
-                .find(|schema| schema.kind == "index" && schema.table_name == query.table);
+                .any(|column| column.eq_ignore_ascii_case(name))
+                || is_rowid(name, fields)
+        })
+    })
+}
 
-            if let Some(index_schema) = index_schema {
-                let (_, index_info) = parse_create_index(index_schema.sql.as_bytes()).unwrap();
-                if index_info.column_name == key {
-                    idx_info = Some(index_info);
+/// Records built from index entries, with the keys as the values of the indexed columns.
+/// The other columns are left NULL, covered_by_index makes sure they aren't needed.
+fn index_only_records(
+    entries: Vec<(usize, Vec<Value>)>,
+    index_columns: &[String],
+    fields: &HashMap<String, (usize, Field)>,
+) -> Vec<Record> {
+    let width = fields.values().map(|(ind, _)| ind + 1).max().unwrap_or(0);
+    entries
+        .into_iter()
+        .map(|(row_id, index_record)| {
+            let mut columns = vec![Value::Null; width];
+            for (column, key) in index_columns.iter().zip(index_record) {
+                if let Some((ind, _)) = field(column, fields) {
+                    columns[*ind] = key;
                 }
             }
-        }
+            Record {
+                row_id: row_id as i64,
+                columns,
+            }
+        })
+        .collect()
+}
 
-        let fields = self.get_fields_in_table(&query.table)?;
+const ROWID_ALIASES: [&str; 3] = ["rowid", "oid", "_rowid_"];
 
-        let records = if let Some(index_info) = idx_info {
-            // Get records using index
+/// The field of a column, whose name is matched ignoring case like sqlite does.
+fn field<'a>(
+    name: &str,
+    fields: &'a HashMap<String, (usize, Field)>,
+) -> Option<&'a (usize, Field)> {
+    fields.get(name).or_else(|| {
+        fields
+            .iter()
+            .find(|(field_name, _)| field_name.eq_ignore_ascii_case(name))
+            .map(|(_, field)| field)
+    })
+}
 
-            let (_k, value) = &query.where_clause.clone().unwrap();
-            // Get all matching rowIds
-            let row_ids = self.get_row_ids_using_index(index_info, &value)?;
+/// The name of a column as the table declares it, which is how resolve_names rewrites every
+/// column the query references. Rowid aliases that aren't shadowed by a real column are kept
+/// as they are. None if the table has no such column.
+fn declared_name(name: &str, fields: &HashMap<String, (usize, Field)>) -> Option<String> {
+    if fields.contains_key(name) {
+        return Some(name.to_owned());
+    }
+    match fields
+        .keys()
+        .find(|field_name| field_name.eq_ignore_ascii_case(name))
+    {
+        Some(field_name) => Some(field_name.clone()),
+        None => ROWID_ALIASES
+            .iter()
+            .any(|alias| alias.eq_ignore_ascii_case(name))
+            .then(|| name.to_owned()),
+    }
+}
 
-            let schema = self
-                .schemas
-                .iter()
-                .find(|schema| schema.table_name == query.table)
-                .unwrap();
+/// Whether the table has a column of this name, counting the rowid aliases that aren't
+/// shadowed by a real column.
+fn has_column(name: &str, fields: &HashMap<String, (usize, Field)>) -> bool {
+    declared_name(name, fields).is_some()
+}
 
-            let records: Vec<Record> = row_ids
-                .iter()
-                .map(|row_id| self.get_record_by_row_id(*row_id as u64, schema.root_page as usize))
-                .collect();
+/// Whether the column holds the rowid, either through a rowid alias or as INTEGER PRIMARY KEY.
+fn is_rowid(name: &str, fields: &HashMap<String, (usize, Field)>) -> bool {
+    match field(name, fields) {
+        Some((_, field)) => field.is_primary_key,
+        None => has_column(name, fields),
+    }
+}
 
-            records
-        } else {
-            // Get records using full scan
-            let schema = self
-                .schemas
-                .iter()
-                .find(|schema| schema.table_name == query.table).unwrap();
-
-            let mut records =
-                self.get_all_records_for_table(self.page_size as usize, schema.root_page as usize);
-
-            //  filter by where clause
-            if let Some((k, v)) = &query.where_clause {
-                records = records
-                    .into_iter()
-                    .filter(|record| {
-                        let (ind, field) = &fields[k];
-                        let value = util::get_value_for_record(record, *ind, field);
-                        value == *v
-                    })
-                    .collect();
-            };
+/// The value of a column for a record. Columns missing from the fields are rowid aliases,
+/// resolve_names has already rejected any other name.
+fn column_value(record: &Record, column: &str, fields: &HashMap<String, (usize, Field)>) -> Value {
+    match field(column, fields) {
+        Some((ind, field)) => util::get_value_for_record(record, *ind, field),
+        None => Value::Int(record.row_id),
+    }
+}
 
-            records
-        };
+/// The values of all columns of a record in table order, with the rowid as the value of an
+/// INTEGER PRIMARY KEY.
+fn record_values(record: &Record, fields: &HashMap<String, (usize, Field)>) -> Vec<Value> {
+    fields
+        .values()
+        .sorted_by_key(|(ind, _)| *ind)
+        .map(|(ind, field)| util::get_value_for_record(record, *ind, field))
+        .collect()
+}
 
-        match query.select_clause {
-            SelectClause::Columns(columns) => {
-                records.iter().for_each(|record| {
-                    let resp = columns
-                        .iter()
-                        .map(|col| {
-                            let (ind, field) = &fields[col];
-                            util::get_value_for_record(record, *ind, field)
-                        })
-                        .join("|");
-
-                    println!("{}", resp);
-                });
-            }
-            SelectClause::FunctionCall(function_name) => {
-                if function_name.eq_ignore_ascii_case("COUNT") {
-                    println!("{}", records.len());
-                }
+/// Fields of the records of a join of two tables, which hold the columns of the left table
+/// followed by those of the right table. Every column can be referenced as `table.column`, and
+/// by its bare name unless both tables have a column of that name. The INTEGER PRIMARY KEY is
+/// a plain column here, as its value is copied from the rowid into the joined record.
+fn joined_fields(
+    tables: &[String; 2],
+    table_fields: &[HashMap<String, (usize, Field)>; 2],
+) -> HashMap<String, (usize, Field)> {
+    let mut fields = HashMap::new();
+    let mut offset = 0;
+    for (i, table) in tables.iter().enumerate() {
+        let other_fields = &table_fields[1 - i];
+        for (name, (ind, field)) in &table_fields[i] {
+            let entry = (
+                offset + ind,
+                Field {
+                    is_primary_key: false,
+                    ..field.clone()
+                },
+            );
+            if !other_fields.contains_key(name) {
+                fields.insert(name.clone(), entry.clone());
             }
+            fields.insert(format!("{}.{}", table, name), entry);
         }
-
-        return Ok(());
+        offset += table_fields[i].len();
     }
+    fields
+}
 
-    // Get all rowIds filtered by given value
-    fn get_row_ids_using_index(
-        &self,
-        index_info: IndexInfo,
-        value: &str,
-    ) -> Result<Vec<usize>> {
-        // Get index schema
-        let schema = self
-            .schemas
-            .iter()
-            .find(|schema| schema.kind == "index" && schema.name == index_info.index_name)
-            .unwrap();
-
-        // collect all rowIds in this vec
-        let mut row_ids: Vec<usize> = Vec::new();
-
-        self.parse_index_page(
-            self.page_size,
-            schema.root_page as usize,
-            &mut row_ids,
-            value,
-        )?;
+/// Evaluate a where condition against a record.
+fn condition_matches(
+    condition: &WhereCondition,
+    record: &Record,
+    fields: &HashMap<String, (usize, Field)>,
+) -> Result<bool> {
+    // Comparing NULL to anything is NULL, which doesn't match. NOT of a NULL is still NULL,
+    // so rows where the column is NULL match neither a condition nor its negation. Only
+    // IS NULL matches them.
+    let value = column_value(record, condition.column(), fields);
+    if value == Value::Null {
+        return Ok(matches!(condition, WhereCondition::IsNull(_)));
+    }
 
-        return Ok(row_ids);
+    // The literals already have the affinity of the column, so `id = '42'` matches the number
+    // 42 while text like '100' in a TEXT column compares as text. Literals are never NULL.
+    let compare = |literal: &Value| value.compare(literal);
+    match condition {
+        WhereCondition::Equals(_, expected) => Ok(compare(expected).is_eq()),
+        WhereCondition::Compare {
+            op,
+            value: expected,
+            ..
+        } => Ok(op.holds(compare(expected))),
+        WhereCondition::In(_, values) => {
+            Ok(values.iter().any(|candidate| compare(candidate).is_eq()))
+        }
+        WhereCondition::Like {
+            pattern, escape, ..
+        } => pattern::like(&value.to_string(), pattern, escape.as_deref()),
+        WhereCondition::Glob { pattern, .. } => Ok(pattern::glob(&value.to_string(), pattern)),
+        WhereCondition::Between { low, high, .. } => {
+            Ok(compare(low).is_ge() && compare(high).is_le())
+        }
+        WhereCondition::Not(condition) => Ok(!condition_matches(condition, record, fields)?),
+        WhereCondition::IsNull(_) => Ok(false),
+        WhereCondition::IsNotNull(_) => Ok(true),
     }
+}
 
-    /// utility function to read a page from DB
-    fn read_page(&self, page_number: usize) -> Result<Vec<u8>> {
-        util::read_page(&self.file, self.page_size, page_number)
+/// Evaluate a where clause against a record.
+/// AND and OR stop at the first term that decides the result.
+fn expr_matches(
+    expr: &WhereExpr,
+    record: &Record,
+    fields: &HashMap<String, (usize, Field)>,
+) -> Result<bool> {
+    match expr {
+        WhereExpr::Condition(condition) => condition_matches(condition, record, fields),
+        WhereExpr::And(terms) => {
+            for term in terms {
+                if !expr_matches(term, record, fields)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        WhereExpr::Or(terms) => {
+            for term in terms {
+                if expr_matches(term, record, fields)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
     }
+}
 
-    /// Get a single record by row_id. Does a btree traversal.
-    fn get_record_by_row_id(&self, row_id: u64, page_number: usize) -> Record {
-        // Start index of the page
-        let page = self.read_page(page_number).unwrap();
-        let page_header = get_page_header(&page).unwrap();
+/// Resolve the column names used by the query against the fields of its table.
+/// `*` is expanded to every column in declared order.
+/// WHERE and GROUP BY refer to table columns first and fall back to output aliases,
+/// while ORDER BY refers to output aliases first, like sqlite.
+/// Names are matched ignoring case and every column is rewritten to its declared name.
+/// Returns the names of the result columns. Like sqlite, plain columns are named as the table
+/// declares them and everything else as the query writes it.
+fn resolve_names(query: &mut Sql, fields: &HashMap<String, (usize, Field)>) -> Result<Vec<String>> {
+    query.select_clause = query
+        .select_clause
+        .drain(..)
+        .flat_map(|(item, alias)| match item {
+            SelectItem::All => all_columns(fields)
+                .into_iter()
+                .map(|column| (SelectItem::Column(column), None))
+                .collect(),
+            item => vec![(item, alias)],
+        })
+        .collect();
+    let written_names = query.column_names();
 
-        // Get all the cell pointers
-        let cell_pointers =
-            parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells);
+    let resolve_item = |item: &mut SelectItem| -> Result<()> {
+        for column in item.columns_mut() {
+            match declared_name(column, fields) {
+                Some(name) => *column = name,
+                None => bail!("no such column: {}", column),
+            }
+        }
+        Ok(())
+    };
+    for (item, _) in query.select_clause.iter_mut() {
+        resolve_item(item)?;
+    }
+
+    let base_column = |name: &String| -> Result<String> {
+        if let Some(name) = declared_name(name, fields) {
+            return Ok(name);
+        }
+        match query.aliased_item(name) {
+            Some(SelectItem::Column(column)) => Ok(column.clone()),
+            _ => bail!("no such column: {}", name),
+        }
+    };
 
-        // If it is an interior table. the content of the cell pointer are pointers to the left pages
-        if page_header.page_type == BTreePage::InteriorTable {
-            for cp in cell_pointers.iter() {
-                let left_child_pointer_start = *cp as usize;
-                let left_child_pointer_bytes =
-                    &page[left_child_pointer_start..left_child_pointer_start + 4];
-                let left_child_pointer =
-                    u32::from_be_bytes(left_child_pointer_bytes.try_into().unwrap()) as usize;
-                let (key, _offset) = parse_varint(&page[left_child_pointer_start + 4..]);
-                // Recursively get records from the left child pointer
-                if row_id <= (key as u64) {
-                    return self.get_record_by_row_id(row_id, left_child_pointer);
+    let where_columns = query
+        .where_clause
+        .iter()
+        .flat_map(|expr| expr.conditions())
+        .map(|condition| base_column(condition.column()))
+        .collect::<Result<Vec<_>>>()?;
+    let group_by = query
+        .group_by
+        .iter()
+        .map(base_column)
+        .collect::<Result<Vec<_>>>()?;
+    let order_by = query
+        .order_by
+        .iter()
+        .map(|(item, descending)| {
+            let mut item = match item {
+                SelectItem::Column(name) => query.aliased_item(name).unwrap_or(item),
+                SelectItem::FunctionCall(call) if !query.is_aggregate() => {
+                    bail!("misuse of aggregate: {}()", call.name)
                 }
+                _ => item,
             }
+            .clone();
+            resolve_item(&mut item)?;
+            Ok((item, *descending))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-            return self
-                .get_record_by_row_id(row_id, page_header.right_most_pointer.unwrap() as usize);
+    let conditions = query
+        .where_clause
+        .iter_mut()
+        .flat_map(|expr| expr.conditions_mut());
+    for (condition, column) in conditions.zip(where_columns) {
+        // Literals are converted once to the affinity of their column, the rowid's is INTEGER
+        let affinity =
+            field(&column, fields).map_or(Affinity::Integer, |(_, field)| field.affinity());
+        for value in condition.values_mut() {
+            *value = util::apply_affinity(value, affinity);
         }
+        *condition.column_mut() = column;
+    }
+    query.group_by = group_by;
+    query.order_by = order_by;
 
-        // If it is a leaf page. get the records directly
-        if page_header.page_type == BTreePage::LeafTable {
-            for cell_pointer in cell_pointers.into_iter() {
-                let stream = &page[cell_pointer as usize..];
-                let (_payload_size, offset) = parse_varint(stream); // total number of bytes of payload
-                let (key, read_bytes) = parse_varint(&stream[offset..]); // integer key (rowid).
-
-                if (key as u64) != row_id {
-                    continue;
-                }
+    Ok(query
+        .column_names()
+        .into_iter()
+        .zip(written_names)
+        .zip(&query.select_clause)
+        .map(|((declared, written), (item, _))| match item {
+            SelectItem::Column(_) => declared,
+            _ => written,
+        })
+        .collect())
+}
 
-                // Now the actual content start
-                let record = parse_record(&stream[offset + read_bytes..]).unwrap();
+/// Check that every column selected outside of an aggregate is grouped by. Otherwise its value
+/// comes from the last record of each group, which strict mode rejects. Names must already be
+/// resolved.
+fn check_grouped_columns(query: &Sql, fields: &HashMap<String, (usize, Field)>) -> Result<()> {
+    if query.group_by.is_empty() {
+        return Ok(());
+    }
+    // A column of a join can be named with or without its table
+    let position = |name: &str| field(name, fields).map(|(ind, _)| *ind);
+    let is_grouped = |column: &str| {
+        query.group_by.iter().any(|grouped| {
+            grouped == column
+                || position(grouped).is_some() && position(grouped) == position(column)
+        })
+    };
 
-                let record: Vec<String> = record
-                    .iter()
-                    .map(|value| String::from_utf8_lossy(value).into())
-                    .collect();
+    for (item, _) in &query.select_clause {
+        if matches!(item, SelectItem::FunctionCall(_)) {
+            continue;
+        }
+        if let Some(column) = item
+            .columns()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|column| !is_grouped(column))
+        {
+            bail!(
+                "column {} must appear in the GROUP BY clause or be used in an aggregate function",
+                column
+            );
+        }
+    }
+    Ok(())
+}
 
-                return Record {
-                    row_id: key.to_string(),
-                    columns: record,
-                };
+/// Drop the table from the columns of a single table query that are qualified with it, like
+/// `companies.name`. Columns qualified with another table are left as they are, so
+/// resolve_names reports them as missing.
+fn unqualify_columns(query: &mut Sql) {
+    let table = query.table.clone();
+    let unqualify = |column: &mut String| {
+        if let Some((qualifier, name)) = column.split_once('.') {
+            if qualifier.eq_ignore_ascii_case(&table) {
+                *column = name.to_owned();
             }
         }
+    };
 
-        unreachable!("if row_id exists so should the row");
+    let items = query
+        .select_clause
+        .iter_mut()
+        .map(|(item, _)| item)
+        .chain(query.order_by.iter_mut().map(|(item, _)| item));
+    for item in items {
+        item.columns_mut().into_iter().for_each(unqualify);
     }
+    for expr in query.where_clause.iter_mut() {
+        for condition in expr.conditions_mut() {
+            unqualify(condition.column_mut());
+        }
+    }
+    query.group_by.iter_mut().for_each(unqualify);
+}
 
-    /// Get fields in a table indexed by the the field name.
-    fn get_fields_in_table(&self, tablename: &str) -> Result<HashMap<String, (usize, Field)>> {
-        let schema = &self
-            .schemas
-            .iter()
-            .find(|schema| schema.table_name == tablename)
-            .unwrap();
-
-        let (_, create_statement) = parse_creation(schema.sql.as_bytes()).unwrap();
+/// Names of all columns in declared order. A column with several names, like the columns of a
+/// join that can be qualified with their table, is listed by its shortest name.
+fn all_columns(fields: &HashMap<String, (usize, Field)>) -> Vec<String> {
+    fields
+        .iter()
+        .map(|(name, (ind, _))| (*ind, name))
+        .sorted_by_key(|&(ind, name)| (ind, name.len()))
+        .dedup_by(|(a, _), (b, _)| a == b)
+        .map(|(_, name)| name.clone())
+        .collect()
+}
 
-        return Ok(create_statement
-            .fields
-            .into_iter()
-            .enumerate()
-            .map(|(ind, field)| (field.name.clone(), (ind, field)))
-            .collect());
+/// Evaluate a select item over a group of records.
+/// Bare columns take their value from the last record of the group like sqlite.
+fn evaluate_item(
+    item: &SelectItem,
+    group: &[&Record],
+    fields: &HashMap<String, (usize, Field)>,
+) -> Value {
+    match item {
+        SelectItem::All => unreachable!("`*` is expanded by resolve_names"),
+        SelectItem::Column(col) => group
+            .last()
+            .map_or(Value::Null, |record| column_value(record, col, fields)),
+        SelectItem::FunctionCall(call) => evaluate_aggregate(call, group, fields),
+        SelectItem::ScalarCall(call) => group
+            .last()
+            .map_or(Value::Null, |record| evaluate_scalar(call, record, fields)),
+        SelectItem::Expression(expression) => group.last().map_or(Value::Null, |record| {
+            evaluate_operand(expression, record, fields)
+        }),
     }
+}
 
-    // Get records from the given page.
-    fn get_all_records_for_table(&self, page_size: usize, page_number: usize) -> Vec<Record> {
-        let page = self.read_page(page_number).unwrap();
+/// Evaluate a function argument or arithmetic expression for a record.
+fn evaluate_operand(
+    operand: &Operand,
+    record: &Record,
+    fields: &HashMap<String, (usize, Field)>,
+) -> Value {
+    match operand {
+        Operand::Column(column) => column_value(record, column, fields),
+        Operand::Literal(value) => value.clone(),
+        Operand::Null => Value::Null,
+        Operand::Call(call) => evaluate_scalar(call, record, fields),
+        Operand::Arithmetic { left, op, right } => arithmetic::apply(
+            *op,
+            &evaluate_operand(left, record, fields),
+            &evaluate_operand(right, record, fields),
+        ),
+    }
+}
 
-        // get Page header of the current page
-        let page_header = get_page_header(&page).unwrap();
+/// Evaluate a scalar function for a record.
+fn evaluate_scalar(
+    call: &ScalarCall,
+    record: &Record,
+    fields: &HashMap<String, (usize, Field)>,
+) -> Value {
+    let values: Vec<Value> = call
+        .arguments
+        .iter()
+        .map(|argument| evaluate_operand(argument, record, fields))
+        .collect();
+    let arguments: Vec<Option<String>> = values.iter().map(Value::to_text).collect();
 
-        // Get all the cell pointers
-        let cell_pointers =
-            parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells);
+    match call.name.to_ascii_uppercase().as_str() {
+        // A NULL format gives NULL
+        "PRINTF" | "FORMAT" => match arguments.split_first() {
+            Some((Some(format), arguments)) => Value::Text(printf::printf(format, arguments)),
+            _ => Value::Null,
+        },
+        "DATE" => datetime::date(&arguments).map_or(Value::Null, Value::Text),
+        "DATETIME" => datetime::datetime(&arguments).map_or(Value::Null, Value::Text),
+        "ABS" => match values.as_slice() {
+            [value] => arithmetic::abs(value),
+            _ => Value::Null,
+        },
+        "JSON_EXTRACT" => json::json_extract(&arguments),
+        _ => Value::Null,
+    }
+}
 
-        // If it is an interior table. the content of the cell pointer are pointers to the left pages
-        if page_header.page_type == BTreePage::InteriorTable {
-            let mut records: Vec<Record> = cell_pointers
-                .iter()
-                .map(|cell_pointer| {
-                    let left_child_pointer_start = *cell_pointer as usize;
-                    let left_child_pointer_bytes =
-                        &page[left_child_pointer_start..left_child_pointer_start + 4];
-                    let left_child_pointer =
-                        u32::from_be_bytes(left_child_pointer_bytes.try_into().unwrap()) as usize;
-                    // Recursively get records from the left child pointer
-                    return self.get_all_records_for_table(page_size, left_child_pointer);
-                })
-                .flatten()
-                .collect();
+/// Compute an aggregate function over the records of a single group.
+/// Within the group DISTINCT drops repeated argument values before aggregating.
+fn evaluate_aggregate(
+    call: &FunctionCall,
+    group: &[&Record],
+    fields: &HashMap<String, (usize, Field)>,
+) -> Value {
+    let values: Vec<Value> = match &call.argument {
+        Some(column) => group
+            .iter()
+            .map(|record| column_value(record, column, fields))
+            .collect(),
+        // `*` counts every record, so each gets a non-NULL value
+        None => vec![Value::Int(1); group.len()],
+    };
 
-            records.extend(
-                self.get_all_records_for_table(page_size, page_header.right_most_pointer.unwrap() as usize),
-            );
+    let values = if call.distinct {
+        let mut seen = BTreeSet::new();
+        values
+            .into_iter()
+            .filter(|value| seen.insert(ValueKey(value.clone())))
+            .collect()
+    } else {
+        values
+    };
 
-            return records;
+    match call.name.to_ascii_uppercase().as_str() {
+        // The non-NULL values
+        "COUNT" => Value::Int(values.iter().filter(|value| **value != Value::Null).count() as i64),
+        "SUM" => sum_values(&values),
+        // Like SUM but always a REAL, and 0.0 instead of NULL when there is nothing to add
+        "TOTAL" => Value::Float(
+            numeric_values(&values)
+                .map(|(_, number)| number)
+                .sum::<f64>(),
+        ),
+        // Always a REAL, NULL when there is nothing to average
+        "AVG" => {
+            let numbers: Vec<f64> = numeric_values(&values).map(|(_, number)| number).collect();
+            if numbers.is_empty() {
+                Value::Null
+            } else {
+                Value::Float(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
         }
+        // Compared like ORDER BY sorts, so they work on numbers as well as text
+        "MIN" => values
+            .into_iter()
+            .filter(|value| *value != Value::Null)
+            .min_by(Value::compare)
+            .unwrap_or(Value::Null),
+        "MAX" => values
+            .into_iter()
+            .max_by(Value::compare)
+            .unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
 
-        // If it is a leaf page. get the records directly
-        if page_header.page_type == BTreePage::LeafTable {
-            let records = cell_pointers
-                .into_iter()
-                .map(|cell_pointer| {
-                    let stream = &page[(cell_pointer as usize)..];
-                    let (_payload_size, offset) = parse_varint(stream); // total number of bytes of payload
-                    let (row_id, read_bytes) = parse_varint(&stream[offset..]); // integer key (rowid).
-
-                    // Now the actual content start
-                    let record = parse_record(&stream[offset + read_bytes..]).unwrap();
-
-                    let record: Vec<String> = record
-                        .iter()
-                        .map(|value| String::from_utf8_lossy(value).into())
-                        .collect();
-
-                    Record {
-                        row_id: row_id.to_string(),
-                        columns: record,
-                    }
-                })
-                .collect::<Vec<Record>>();
+/// Aggregate functions the engine can evaluate
+const AGGREGATE_FUNCTIONS: [&str; 6] = ["COUNT", "SUM", "TOTAL", "AVG", "MIN", "MAX"];
 
-            return records;
+/// The non-NULL values as numbers, with their integer value if they are integers.
+/// Text that isn't a number counts as 0 like in sqlite.
+fn numeric_values(values: &[Value]) -> impl Iterator<Item = (Option<i64>, f64)> + '_ {
+    values.iter().filter_map(|value| match value {
+        Value::Null => None,
+        Value::Int(int) => Some((Some(*int), *int as f64)),
+        Value::Float(float) => Some((None, *float)),
+        value => {
+            let text = value.to_text().unwrap_or_default();
+            Some(match text.parse::<i64>() {
+                Ok(int) => (Some(int), int as f64),
+                Err(_) => (None, text.parse::<f64>().unwrap_or(0.0)),
+            })
         }
+    })
+}
 
-        return vec![];
+/// SUM of the values: an integer while every value is one, a REAL otherwise.
+/// NULL when there are no non-NULL values.
+fn sum_values(values: &[Value]) -> Value {
+    let mut int_sum = Some(0i64);
+    let mut float_sum = 0.0;
+    let mut any = false;
+    for (int, float) in numeric_values(values) {
+        any = true;
+        int_sum = int_sum.zip(int).and_then(|(sum, int)| sum.checked_add(int));
+        float_sum += float;
     }
 
-    // Get all records from the index page 
-    fn parse_index_page(
-        &self,
-        page_size: u16,
-        page_number: usize,
-        row_collector: &mut Vec<usize>,
-        value: &str,
-    ) -> Result<()> {
-        let page = self.read_page(page_number)?;
-        // Get the index page
-        let page_header = get_page_header(&page)?;
+    match (any, int_sum) {
+        (false, _) => Value::Null,
+        (true, Some(sum)) => Value::Int(sum),
+        (true, None) => Value::Float(float_sum),
+    }
+}
 
+/// Signs that another connection is writing the database at `path`: a non-empty rollback
+/// journal or write-ahead log next to it. Their changes aren't visible when reading the main
+/// file alone, so query results may be stale or inconsistent.
+pub fn pending_writes(path: &Path) -> Vec<String> {
+    ["-journal", "-wal"]
+        .into_iter()
+        .filter_map(|suffix| {
+            let mut side_file = path.as_os_str().to_owned();
+            side_file.push(suffix);
+            let size = fs::metadata(&side_file).ok()?.len();
+            (size > 0).then(|| {
+                format!(
+                    "{} exists, the database may be being written",
+                    Path::new(&side_file).display()
+                )
+            })
+        })
+        .collect()
+}
 
-        if page_header.page_type == BTreePage::InteriorIndex {
-            let cell_pointers =
-        // get the index cell pointers
-        parse_cell_pointers(&page[12..], page_header.number_of_cells);
+/// Names of the result columns of a PRAGMA, matching sqlite's.
+fn pragma_column_names(name: &str) -> Vec<String> {
+    let columns: &[&str] = match name.to_ascii_lowercase().as_str() {
+        "table_info" => &["cid", "name", "type", "notnull", "dflt_value", "pk"],
+        "index_list" => &["seq", "name", "unique", "origin", "partial"],
+        "index_info" => &["seqno", "cid", "name"],
+        "page_size" => &["page_size"],
+        "page_count" => &["page_count"],
+        "user_version" => &["user_version"],
+        "application_id" => &["application_id"],
+        _ => &[],
+    };
+    columns.iter().map(|column| column.to_string()).collect()
+}
 
-            for cell_pointer in cell_pointers.iter() {
-                let left_child_pointer_start = *cell_pointer as usize;
+pub struct DB {
+    pub page_size: u16,
+    pub reserved_bytes: u8, // unused space at the end of each page, from the database header
+    pub schemas: Vec<Schema>,
+    pub file: File,
+    pub strict: bool, // error on unsupported features instead of giving partial or arbitrary values
+    page_reads: Cell<usize>,
+    disk_reads: Cell<usize>,
+    page_cache: RefCell<HashMap<usize, Vec<u8>>>,
+}
 
-                let left_child_pointer_bytes =
-                    &page[left_child_pointer_start..left_child_pointer_start + 4];
+/// Most pages kept in the page cache. It is emptied when full.
+const PAGE_CACHE_CAPACITY: usize = 2048;
 
-                let left_child_pointer =
-                    u32::from_be_bytes(left_child_pointer_bytes.try_into().unwrap()) as usize;
-                let mut offset = 4;
+pub struct Record {
+    pub row_id: i64,
+    pub columns: Vec<Value>,
+}
 
-                let (_payload_size, payload_offset) =
-                    parse_varint(&page[(left_child_pointer_start + offset)..]);
-                offset += payload_offset;
+/// The result of a statement: the names of its result columns and its rows.
+#[derive(Debug, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
 
-                let record = parse_record(&page[(left_child_pointer_start + offset)..]).unwrap();
+impl DB {
+    /// Create a new instance of the DB.
+    pub fn new(page_size: u16, schemas: Vec<Schema>, file: File) -> Self {
+        Self {
+            page_size,
+            reserved_bytes: 0,
+            schemas,
+            file,
+            strict: false,
+            page_reads: Cell::new(0),
+            disk_reads: Cell::new(0),
+            page_cache: RefCell::new(HashMap::new()),
+        }
+    }
 
-                let key = String::from_utf8_lossy(&record[0]);
+    /// Open the database at `path`. The page size and reserved bytes are taken from its header
+    /// and the schema is loaded, so the database is ready to be queried.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut header = [0; 100];
+        file.read_exact_at(&mut header, 0)?;
+        let header = DatabaseHeader::parse(&header)?;
 
-                // If value_to_check > cur_key no need to check left tree
-                if value > &key {
-                    continue;
-                }
+        let schemas = read_schemas(&file, header.page_size, header.usable_size())?;
+        let mut db = Self::new(header.page_size, schemas, file);
+        db.reserved_bytes = header.reserved_bytes;
+        Ok(db)
+    }
 
-                // value_to_check == cur_key then check left pointer as well.
-                if value == &key {
-                    let rowid = record[1].clone();
-                    let rowid = parse_24bit_be_twos_complement(&rowid);
-                    row_collector.push(rowid as usize);
-                }
+    /// Open the database at `path` using the given page size instead of the one in the header,
+    /// to salvage data from a file whose header page size is damaged.
+    /// The page size must be a power of two from 512 to 32768.
+    pub fn open_with_page_size<P: AsRef<Path>>(path: P, page_size: u16) -> Result<Self> {
+        if page_size < 512 || !page_size.is_power_of_two() {
+            bail!(
+                "Invalid page size: {}, must be a power of two from 512 to 32768",
+                page_size
+            );
+        }
 
-                self.parse_index_page(page_size, left_child_pointer as usize, row_collector, value)
-                    .unwrap();
+        // The reserved space is taken from the header all the same, it can't be guessed
+        let file = File::open(path)?;
+        let mut reserved_bytes = [0];
+        file.read_exact_at(&mut reserved_bytes, 20)?;
+        let usable_size = page_size as usize - reserved_bytes[0] as usize;
+        let schemas = read_schemas(&file, page_size, usable_size)?;
+        let mut db = Self::new(page_size, schemas, file);
+        db.reserved_bytes = reserved_bytes[0];
+        Ok(db)
+    }
 
-                // if value_to_check < cur_key. Need to check the left_pointer 1 last time.
-                if value < &key {
-                    break;
+    /// Returns an error for the first function in the query that the engine doesn't know.
+    /// Any name parses as a call with a single argument, like an aggregate function, and
+    /// only the calls taking `*` or DISTINCT are left to a scalar function.
+    /// Checked in every mode, an unknown function never has a value to give.
+    fn check_functions(&self, query: &Sql) -> Result<()> {
+        let items = query.select_clause.iter().map(|(item, _)| item);
+        for item in items.chain(query.order_by.iter().map(|(item, _)| item)) {
+            if let SelectItem::FunctionCall(call) = item {
+                let is_one_of = |names: &[&str]| {
+                    names
+                        .iter()
+                        .any(|name| call.name.eq_ignore_ascii_case(name))
+                };
+                if is_one_of(&SCALAR_FUNCTIONS) {
+                    bail!(
+                        "{}() is not an aggregate function, it can't take * or DISTINCT",
+                        call.name
+                    );
+                }
+                if !is_one_of(&AGGREGATE_FUNCTIONS) {
+                    bail!("no such function: {}", call.name);
                 }
             }
+        }
 
-            self.parse_index_page(
-                page_size,
-                page_header.right_most_pointer.unwrap() as usize,
-                row_collector,
-                value,
-            )
-            .expect("Surely there is a right most pointer");
+        Ok(())
+    }
 
-            return Ok(());
+    /// Execute any statement and return its result.
+    pub fn execute_statement(&self, statement: Statement) -> Result<QueryResult> {
+        match statement {
+            Statement::Select(query) => self.execute_query(*query),
+            Statement::Pragma(pragma) => Ok(QueryResult {
+                columns: pragma_column_names(&pragma.name),
+                rows: self.execute_pragma(&pragma)?,
+            }),
         }
-        if page_header.page_type == BTreePage::LeafIndex {
-            let cell_pointers = parse_cell_pointers(&page[8..], page_header.number_of_cells);
+    }
 
-            for cell_pointer in &cell_pointers {
-                let cell_pointer_start = *cell_pointer as usize;
+    /// Execute a PRAGMA and return its rows.
+    /// Unknown pragmas return no rows like sqlite, unless in strict mode.
+    pub fn execute_pragma(&self, pragma: &Pragma) -> Result<Vec<Vec<Value>>> {
+        match (pragma.name.to_ascii_lowercase().as_str(), &pragma.argument) {
+            ("table_info", Some(table)) => self.table_info(table),
+            ("index_list", Some(table)) => self.index_list(table),
+            ("index_info", Some(index)) => self.index_info(index),
+            ("page_size", None) => Ok(vec![vec![Value::Int(self.page_size.into())]]),
+            ("page_count", None) => Ok(vec![vec![Value::Int(self.page_count()? as i64)]]),
+            ("user_version", None) => Ok(vec![vec![Value::Int(
+                self.read_header()?.user_version.into(),
+            )]]),
+            ("application_id", None) => Ok(vec![vec![Value::Int(
+                self.read_header()?.application_id.into(),
+            )]]),
+            _ if self.strict => bail!("Unsupported pragma: {}", pragma.name),
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Rows of `PRAGMA table_info`: (cid, name, type, notnull, dflt_value, pk) per column.
+    /// A table that doesn't exist has no rows.
+    fn table_info(&self, table: &str) -> Result<Vec<Vec<Value>>> {
+        let schema = self
+            .schemas
+            .iter()
+            .find(|schema| schema.kind == "table" && schema.name.eq_ignore_ascii_case(table));
+        let Some(schema) = schema else {
+            return Ok(vec![]);
+        };
 
-                let stream = &page[cell_pointer_start as usize..];
+        let (_, create_statement) = parse_creation(schema.sql.as_bytes())
+            .map_err(|_| anyhow!("Failed to parse schema of {}", table))?;
+        let primary_key = &create_statement.primary_key;
 
-                let key_record = parse_index_payload(stream)?;
+        Ok(create_statement
+            .fields
+            .into_iter()
+            .enumerate()
+            .map(|(cid, field)| {
+                // sqlite reports the standard type names in upper case
+                let data_type = field.data_type.unwrap_or_default();
+                let data_type = match data_type.to_ascii_uppercase().as_str() {
+                    standard @ ("INT" | "INTEGER" | "REAL" | "TEXT" | "BLOB" | "ANY") => {
+                        standard.to_owned()
+                    }
+                    _ => data_type,
+                };
 
-                let key = &key_record[0];
+                // The position of the column in the primary key, counting from 1. The key columns
+                // of a WITHOUT ROWID table are implicitly NOT NULL.
+                let key_position = primary_key
+                    .iter()
+                    .position(|column| column.eq_ignore_ascii_case(&field.name))
+                    .map_or(0, |position| position + 1);
+                let not_null =
+                    field.not_null || (create_statement.without_rowid && key_position > 0);
 
-                if key == value.as_bytes() {
-                    let rowid = key_record[1].clone();
-                    let rowid = parse_24bit_be_twos_complement(&rowid);
+                vec![
+                    Value::Int(cid as i64),
+                    Value::Text(field.name),
+                    Value::Text(data_type),
+                    Value::Int(not_null.into()),
+                    field.default_value.map_or(Value::Null, Value::Text),
+                    Value::Int(key_position as i64),
+                ]
+            })
+            .collect())
+    }
 
-                    row_collector.push(rowid as usize);
-                }
-            }
-        }
+    /// Parsed CREATE INDEX statements of the indexes on a table, most recently created first.
+    /// Indexes sqlite creates automatically for constraints have no sql and are left out.
+    fn indexes_on_table(&self, table: &str) -> Vec<IndexInfo> {
+        self.schemas
+            .iter()
+            .rev()
+            .filter(|schema| {
+                schema.kind == "index" && schema.table_name.eq_ignore_ascii_case(table)
+            })
+            .filter_map(|schema| parse_create_index(schema.sql.as_bytes()).ok())
+            .map(|(_, index_info)| index_info)
+            .collect()
+    }
 
-        Ok(())
+    /// Rows of `PRAGMA index_list`: (seq, name, unique, origin, partial) per index on the table.
+    fn index_list(&self, table: &str) -> Result<Vec<Vec<Value>>> {
+        Ok(self
+            .indexes_on_table(table)
+            .into_iter()
+            .enumerate()
+            .map(|(seq, index_info)| {
+                vec![
+                    Value::Int(seq as i64),
+                    Value::Text(index_info.index_name),
+                    Value::Int(index_info.unique.into()),
+                    Value::Text("c".to_owned()), // created by CREATE INDEX
+                    Value::Int(0),
+                ]
+            })
+            .collect())
+    }
+
+    /// Rows of `PRAGMA index_info`: (seqno, cid, name) per indexed column.
+    /// An index that doesn't exist has no rows.
+    fn index_info(&self, index: &str) -> Result<Vec<Vec<Value>>> {
+        let schema = self
+            .schemas
+            .iter()
+            .find(|schema| schema.kind == "index" && schema.name.eq_ignore_ascii_case(index));
+        let Some(schema) = schema else {
+            return Ok(vec![]);
+        };
+
+        let (_, index_info) = parse_create_index(schema.sql.as_bytes())
+            .map_err(|_| anyhow!("Failed to parse schema of {}", index))?;
+        let fields = self.get_fields_in_table(&index_info.table_name)?;
+        index_info
+            .column_names
+            .into_iter()
+            .enumerate()
+            .map(|(seqno, column_name)| {
+                let (cid, _) = field(&column_name, &fields)
+                    .ok_or_else(|| anyhow!("no such column: {}", column_name))?;
+                Ok(vec![
+                    Value::Int(seqno as i64),
+                    Value::Int(*cid as i64),
+                    Value::Text(column_name),
+                ])
+            })
+            .collect()
+    }
+
+    /// Execute a query and return the names of its result columns and the resulting rows.
+    /// The records are read from the table, or from both tables of a join, and then grouped.
+    /// ORDER BY, LIMIT and OFFSET are applied to the final rows, i.e. after aggregation.
+    /// In strict mode queries using unsupported features error instead.
+    pub fn execute_query(&self, mut query: Sql) -> Result<QueryResult> {
+        self.check_functions(&query)?;
+
+        // Tables are matched ignoring case too, from here on they go by their declared names
+        query.table = self.table_schema(&query.table)?.name.clone();
+        if query.counts_all_rows() {
+            let count = self.count_rows(self.table_schema(&query.table)?.root_page as usize)?;
+            let row = vec![Value::Int(count as i64); query.select_clause.len()];
+            return Ok(QueryResult {
+                columns: query.column_names(),
+                rows: apply_window(vec![row], query.limit, query.offset),
+            });
+        }
+        let (records, fields, columns) = match query.join.take() {
+            Some(mut join) => {
+                join.table = self.table_schema(&join.table)?.name.clone();
+                if join.table == query.table {
+                    bail!("joining a table with itself is not supported");
+                }
+                let table_fields = [
+                    self.get_fields_in_table(&query.table)?,
+                    self.get_fields_in_table(&join.table)?,
+                ];
+                let fields =
+                    joined_fields(&[query.table.clone(), join.table.clone()], &table_fields);
+                let columns = resolve_names(&mut query, &fields)?;
+                if self.strict {
+                    check_grouped_columns(&query, &fields)?;
+                }
+                let records =
+                    self.read_joined_records(&mut query, &join, &table_fields, &fields)?;
+                (records, fields, columns)
+            }
+            None => {
+                let fields = self.get_fields_in_table(&query.table)?;
+                unqualify_columns(&mut query);
+                let columns = resolve_names(&mut query, &fields)?;
+                if self.strict {
+                    check_grouped_columns(&query, &fields)?;
+                }
+                (self.read_records(&query, &fields)?, fields, columns)
+            }
+        };
+        let window_records = window_records(&query, &fields);
+
+        // Every row is computed from a group of records. Without aggregation each record is
+        // a group of its own.
+        let groups: Vec<Vec<&Record>> = if !query.is_aggregate() {
+            records.iter().map(|record| vec![record]).collect()
+        } else if query.group_by.is_empty() {
+            // A plain aggregate always yields exactly one row, even without records
+            vec![records.iter().collect()]
+        } else {
+            // In the order of the group values, like ORDER BY sorts them
+            let mut groups: BTreeMap<Vec<ValueKey>, Vec<&Record>> = BTreeMap::new();
+            for record in records.iter() {
+                let key = query
+                    .group_by
+                    .iter()
+                    .map(|col| ValueKey(column_value(record, col, &fields)))
+                    .collect();
+                groups.entry(key).or_default().push(record);
+            }
+            groups.into_values().collect()
+        };
+
+        // Build each row along with the values it is sorted by
+        let mut rows: Vec<(Vec<Value>, Vec<Value>)> = groups
+            .iter()
+            .map(|group| {
+                let row = query
+                    .select_clause
+                    .iter()
+                    .map(|(item, _)| evaluate_item(item, group, &fields))
+                    .collect();
+                let sort_key = query
+                    .order_by
+                    .iter()
+                    .map(|(item, _)| evaluate_item(item, group, &fields))
+                    .collect();
+                (row, sort_key)
+            })
+            .collect();
+
+        // The sort is stable, so rows with equal sort keys keep their scan order
+        if !query.order_by.is_empty() {
+            rows.sort_by(|(_, a), (_, b)| {
+                a.iter()
+                    .zip(b)
+                    .zip(&query.order_by)
+                    .map(|((a, b), (_, descending))| {
+                        let ordering = a.compare(b);
+                        if *descending {
+                            ordering.reverse()
+                        } else {
+                            ordering
+                        }
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        let rows: Vec<Vec<Value>> = rows.into_iter().map(|(row, _)| row).collect();
+
+        // DISTINCT applies to the final rows (after grouping) and before LIMIT
+        let rows = if query.distinct {
+            let mut seen = BTreeSet::new();
+            rows.into_iter()
+                .filter(|row| seen.insert(row.iter().cloned().map(ValueKey).collect::<Vec<_>>()))
+                .collect()
+        } else {
+            rows
+        };
+
+        let rows = if window_records {
+            rows
+        } else {
+            apply_window(rows, query.limit, query.offset)
+        };
+        Ok(QueryResult { columns, rows })
+    }
+
+    /// Read the records of the query's table that match its where clause.
+    /// Tries to use index if possible.
+    /// Else does a full scan.
+    /// Conditions of the where clause outside of an OR can narrow down the records read.
+    /// When every record becomes one row in scan order the OFFSET and LIMIT are applied too.
+    fn read_records(
+        &self,
+        query: &Sql,
+        fields: &HashMap<String, (usize, Field)>,
+    ) -> Result<Vec<Record>> {
+        let required_conditions = query
+            .where_clause
+            .as_ref()
+            .map(WhereExpr::required_conditions)
+            .unwrap_or_default();
+
+        // The first equality, IN list, BETWEEN or else comparison on the first column of an
+        // index is looked up in the index. As ranges of keys, one for each value of an IN list.
+        // Equalities on the columns after it narrow the ranges down, the index with the most
+        // of them is used.
+        let comparisons_last = required_conditions
+            .iter()
+            .sorted_by_key(|condition| matches!(condition, WhereCondition::Compare { .. }));
+        let index_lookup = comparisons_last.into_iter().find_map(|condition| {
+            let ranges = match condition {
+                WhereCondition::Equals(_, value) => {
+                    vec![(
+                        Bound::Included(value.clone()),
+                        Bound::Included(value.clone()),
+                    )]
+                }
+                WhereCondition::In(_, values) => values
+                    .iter()
+                    .map(|value| {
+                        (
+                            Bound::Included(value.clone()),
+                            Bound::Included(value.clone()),
+                        )
+                    })
+                    .collect(),
+                WhereCondition::Between { low, high, .. } => {
+                    vec![(Bound::Included(low.clone()), Bound::Included(high.clone()))]
+                }
+                // NULL keys come first and never compare as less than a value
+                WhereCondition::Compare { op, value, .. } => vec![match op {
+                    CompareOp::Greater => (Bound::Excluded(value.clone()), Bound::Unbounded),
+                    CompareOp::GreaterEqual => (Bound::Included(value.clone()), Bound::Unbounded),
+                    CompareOp::Less => {
+                        (Bound::Excluded(Value::Null), Bound::Excluded(value.clone()))
+                    }
+                    CompareOp::LessEqual => {
+                        (Bound::Excluded(Value::Null), Bound::Included(value.clone()))
+                    }
+                    CompareOp::NotEqual => return None,
+                }],
+                _ => return None,
+            };
+            let (index_info, prefix) = self
+                .indexes_on_table(&query.table)
+                .into_iter()
+                .filter(|index_info| {
+                    index_info.column_names[0].eq_ignore_ascii_case(condition.column())
+                })
+                .map(|index_info| {
+                    let prefix =
+                        equality_prefix(&index_info.column_names[1..], &required_conditions);
+                    (index_info, prefix)
+                })
+                .max_by_key(|(_, prefix)| prefix.len())?;
+            let ranges = ranges
+                .into_iter()
+                .map(|(low, high)| {
+                    let key = |first| [vec![first], prefix.clone()].concat();
+                    (low.map(key), high.map(key))
+                })
+                .collect::<Vec<_>>();
+            // Only the keys of an equality or IN list are exactly the values the condition
+            // matches, a range is checked against the condition again
+            let exact = matches!(
+                condition,
+                WhereCondition::Equals(..) | WhereCondition::In(..)
+            );
+            Some((index_info, ranges, exact))
+        });
+
+        let window_records = window_records(query, fields);
+        // When the index lookup of a single equality or IN list finds exactly the matching
+        // records, the window can be applied before they are even read
+        let window_before_read = window_records
+            && matches!(query.where_clause, Some(WhereExpr::Condition(_)))
+            && matches!(index_lookup, Some((_, _, true)));
+
+        // `WHERE rowid > n` only needs the part of the table btree after n. Used for keyset
+        // pagination.
+        let first_row_id = required_conditions
+            .iter()
+            .find_map(|condition| match condition {
+                WhereCondition::Compare { column, op, value } if is_rowid(column, fields) => {
+                    match (op, value) {
+                        (CompareOp::Greater, Value::Int(row_id)) => row_id.checked_add(1),
+                        (CompareOp::GreaterEqual, Value::Int(row_id)) => Some(*row_id),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            });
+
+        let schema = self.table_schema(&query.table)?;
+        let (_, create_statement) = parse_creation(schema.sql.as_bytes())
+            .map_err(|_| anyhow!("Failed to parse schema of {}", query.table))?;
+
+        let (records, windowed) = if create_statement.without_rowid {
+            // resolve_names leaves only rowid aliases without a field, which this table lacks
+            let columns = query.referenced_columns().unwrap_or_default();
+            if let Some(name) = columns
+                .iter()
+                .find(|name| !fields.contains_key(name.as_str()))
+            {
+                bail!("no such column: {}", name);
+            }
+            // The table btree is keyed by the primary key, its indexes can't find rows by rowid
+            let records =
+                self.get_without_rowid_records(schema.root_page as usize, &create_statement)?;
+            (Some(records), false)
+        } else if let Some((index_info, ranges, _)) = index_lookup {
+            // Get records using index
+
+            // Get the rowIds and keys of all matching index entries. Each row is fetched and
+            // output once, even if the index yields its rowid more than once or it is in
+            // several ranges. Like a full scan the rows are in rowid order.
+            let mut entries = vec![];
+            for (low, high) in &ranges {
+                let (low, high) = (
+                    low.as_ref().map(Vec::as_slice),
+                    high.as_ref().map(Vec::as_slice),
+                );
+                for index_record in self.get_index_records(&index_info, low, high)? {
+                    entries.push((index_row_id(&index_record)?, index_record));
+                }
+            }
+            entries.sort_unstable_by_key(|(row_id, _)| *row_id);
+            entries.dedup_by_key(|(row_id, _)| *row_id);
+            // Skipped rows don't need their records fetched
+            let entries = if window_before_read {
+                apply_window(entries, query.limit, query.offset)
+            } else {
+                entries
+            };
+
+            let records = if covered_by_index(query, &index_info.column_names, fields) {
+                // The index has every value the query needs, the table isn't read at all
+                index_only_records(entries, &index_info.column_names, fields)
+            } else {
+                let row_ids: Vec<usize> = entries.into_iter().map(|(row_id, _)| row_id).collect();
+                self.get_records_by_row_ids(&row_ids, schema.root_page as usize)?
+            };
+            (Some(records), window_before_read)
+        } else {
+            (None, false)
+        };
+        let records: Box<dyn Iterator<Item = Result<Record>>> = match records {
+            Some(records) => Box::new(records.into_iter().map(Ok)),
+            // Get records using full scan. Pages are read as the records are consumed.
+            None => Box::new(
+                self.scan_table(schema.root_page as usize, first_row_id.unwrap_or(i64::MIN)),
+            ),
+        };
+
+        // Filter by where clause. The scan stops once the rows of the window are collected.
+        let (mut offset, limit) = if window_records && !windowed {
+            (query.offset.unwrap_or(0), query.limit.unwrap_or(usize::MAX))
+        } else {
+            (0, usize::MAX)
+        };
+        let mut matching = vec![];
+        for record in records {
+            if matching.len() >= limit {
+                break;
+            }
+            let record = record?;
+            let matches = match &query.where_clause {
+                Some(expr) => expr_matches(expr, &record, fields)?,
+                None => true,
+            };
+            if !matches {
+                continue;
+            }
+            if offset > 0 {
+                offset -= 1;
+            } else {
+                matching.push(record);
+            }
+        }
+        Ok(matching)
+    }
+
+    /// Read the records of an INNER JOIN. Each joined record holds the columns of the left
+    /// table followed by those of the right table, as described by `fields`. The query's names
+    /// must already be resolved against them.
+    /// Parts of the where clause that only involve one of the tables are applied while
+    /// scanning that table, so an index or rowid range can narrow down its records and fewer
+    /// records get joined. The remaining parts are checked on the joined records.
+    fn read_joined_records(
+        &self,
+        query: &mut Sql,
+        join: &Join,
+        table_fields: &[HashMap<String, (usize, Field)>; 2],
+        fields: &HashMap<String, (usize, Field)>,
+    ) -> Result<Vec<Record>> {
+        let tables = [&query.table, &join.table];
+
+        // The table a column belongs to, along with its declared name within that table
+        let table_of = |column: &str| -> Option<(usize, String)> {
+            let (table, name) = match column.split_once('.') {
+                Some((table, name)) => (
+                    tables.iter().position(|t| t.eq_ignore_ascii_case(table))?,
+                    name,
+                ),
+                None => (
+                    table_fields
+                        .iter()
+                        .position(|fields| has_column(column, fields))?,
+                    column,
+                ),
+            };
+            Some((table, declared_name(name, &table_fields[table])?))
+        };
+
+        let (left_column, right_column) = match (table_of(&join.on.0), table_of(&join.on.1)) {
+            (Some((0, left)), Some((1, right))) | (Some((1, right)), Some((0, left))) => {
+                (left, right)
+            }
+            _ => bail!("unsupported join condition: {} = {}", join.on.0, join.on.1),
+        };
+
+        // Split the top level AND terms of the where clause by the table they involve
+        let mut pushed_down: [Vec<WhereExpr>; 2] = Default::default();
+        let mut remaining = vec![];
+        for mut term in query
+            .where_clause
+            .take()
+            .map(WhereExpr::into_conjuncts)
+            .unwrap_or_default()
+        {
+            let term_tables: Vec<Option<usize>> = term
+                .conditions()
+                .iter()
+                .map(|condition| table_of(condition.column()).map(|(table, _)| table))
+                .unique()
+                .collect();
+            match term_tables.as_slice() {
+                [Some(table)] => {
+                    for condition in term.conditions_mut() {
+                        let (_, name) = table_of(condition.column()).unwrap();
+                        *condition.column_mut() = name;
+                    }
+                    pushed_down[*table].push(term);
+                }
+                _ => remaining.push(term),
+            }
+        }
+
+        let [left_conditions, right_conditions] = pushed_down;
+        let scan = |table: usize, conditions: Vec<WhereExpr>| {
+            let table_query = Sql {
+                table: tables[table].clone(),
+                where_clause: (!conditions.is_empty()).then(|| WhereExpr::and(conditions)),
+                ..Default::default()
+            };
+            self.read_records(&table_query, &table_fields[table])
+        };
+        let left_records = scan(0, left_conditions)?;
+        let right_records = scan(1, right_conditions)?;
+
+        // Records of the right table by their join column value. NULL never equals anything.
+        let mut right_by_key: BTreeMap<ValueKey, Vec<Vec<Value>>> = BTreeMap::new();
+        for record in &right_records {
+            let key = column_value(record, &right_column, &table_fields[1]);
+            if key != Value::Null {
+                right_by_key
+                    .entry(ValueKey(key))
+                    .or_default()
+                    .push(record_values(record, &table_fields[1]));
+            }
+        }
+
+        // Joined in the order of the left table, like a nested loop over both tables
+        let where_clause = (!remaining.is_empty()).then(|| WhereExpr::and(remaining));
+        let mut records = vec![];
+        for left in &left_records {
+            let key = ValueKey(column_value(left, &left_column, &table_fields[0]));
+            let Some(matches) = right_by_key.get(&key) else {
+                continue;
+            };
+            let left_values = record_values(left, &table_fields[0]);
+            for right_values in matches {
+                let record = Record {
+                    row_id: left.row_id,
+                    columns: left_values.iter().chain(right_values).cloned().collect(),
+                };
+                let matches = match &where_clause {
+                    Some(expr) => expr_matches(expr, &record, fields)?,
+                    None => true,
+                };
+                if matches {
+                    records.push(record);
+                }
+            }
+        }
+
+        Ok(if window_records(query, fields) {
+            apply_window(records, query.limit, query.offset)
+        } else {
+            records
+        })
+    }
+
+    // Get all index records, the keys followed by the rowId, with keys from `low` to `high`.
+    // The bounds are values of the first index columns, and only those columns are compared.
+    // For an equality both are the values included.
+    fn get_index_records(
+        &self,
+        index_info: &IndexInfo,
+        low: Bound<&[Value]>,
+        high: Bound<&[Value]>,
+    ) -> Result<Vec<Vec<Value>>> {
+        // Get index schema
+        let schema = self
+            .schemas
+            .iter()
+            .find(|schema| schema.kind == "index" && schema.name == index_info.index_name)
+            .ok_or_else(|| anyhow!("no such index: {}", index_info.index_name))?;
+
+        // collect all index records in this vec
+        let mut index_records = Vec::new();
+
+        self.parse_index_page(
+            schema.root_page as usize,
+            &mut index_records,
+            (low, high),
+            &index_info.descending,
+            &mut HashSet::new(),
+        )?;
+
+        Ok(index_records)
+    }
+
+    /// Decode the btree header of the given page.
+    /// Page 1 starts with the 100 byte database header, so its btree header comes after it.
+    pub fn read_page_header(&self, page_number: usize) -> Result<PageHeader> {
+        if self.is_ptrmap_page(page_number)? {
+            bail!(
+                "Page {} is a pointer map page, not a btree page",
+                page_number
+            );
+        }
+        let page = self.read_page(page_number)?;
+        let offset = if page_number == 1 { 100 } else { 0 };
+        get_page_header(&page[offset..])
+    }
+
+    /// Whether the page is a pointer map page, which only auto_vacuum and incremental_vacuum
+    /// databases have. Tools going through the pages in order must skip these.
+    pub fn is_ptrmap_page(&self, page_number: usize) -> Result<bool> {
+        Ok(self.read_header()?.has_ptrmap()
+            && ptrmap::is_ptrmap_page(page_number, self.usable_size()))
+    }
+
+    /// Decode the entries of a pointer map page.
+    pub fn read_ptrmap_page(&self, page_number: usize) -> Result<Vec<PtrmapEntry>> {
+        if !self.is_ptrmap_page(page_number)? {
+            bail!("Page {} is not a pointer map page", page_number);
+        }
+        let page = self.read_page(page_number)?;
+        ptrmap::parse_ptrmap_page(&page, page_number, self.usable_size())
+    }
+
+    /// Number of bytes of each page that hold data, those before the reserved space.
+    pub fn usable_size(&self) -> usize {
+        self.page_size as usize - self.reserved_bytes as usize
+    }
+
+    /// utility function to read a page from DB, through the page cache
+    fn read_page(&self, page_number: usize) -> Result<Vec<u8>> {
+        self.page_reads.set(self.page_reads.get() + 1);
+        if let Some(page) = self.page_cache.borrow().get(&page_number) {
+            return Ok(page.clone());
+        }
+
+        self.disk_reads.set(self.disk_reads.get() + 1);
+        let page = util::read_page(&self.file, self.page_size, page_number)?;
+        let mut cache = self.page_cache.borrow_mut();
+        if cache.len() >= PAGE_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(page_number, page.clone());
+        Ok(page)
+    }
+
+    /// Parse a cell of a table leaf page into a record.
+    fn parse_leaf_table_cell(&self, stream: &[u8]) -> Result<Record> {
+        let (payload_size, offset) = parse_varint(stream); // total number of bytes of payload
+        let (row_id, read_bytes) = parse_varint(&stream[offset..]); // integer key (rowid).
+
+        // Now the actual content start
+        let local_size = overflow::table_local_payload_size(payload_size, self.usable_size());
+        let payload =
+            self.read_cell_payload(&stream[offset + read_bytes..], payload_size, local_size)?;
+        Ok(Record {
+            row_id: row_id as i64,
+            columns: parse_record(&payload)?,
+        })
+    }
+
+    /// Parse the record of an index cell, starting at its payload size.
+    fn parse_index_cell_payload(&self, stream: &[u8]) -> Result<Vec<Value>> {
+        let (payload_size, offset) = parse_varint(stream);
+        let local_size = overflow::index_local_payload_size(payload_size, self.usable_size());
+        let payload = self.read_cell_payload(&stream[offset..], payload_size, local_size)?;
+        parse_record(&payload)
+    }
+
+    /// Read the payload of a cell starting at `stream`, of which `local_size` bytes are
+    /// stored in the cell. The rest is read from the chain of overflow pages.
+    fn read_cell_payload(
+        &self,
+        stream: &[u8],
+        payload_size: usize,
+        local_size: usize,
+    ) -> Result<Vec<u8>> {
+        let usable_size = self.usable_size();
+        overflow::read_payload(
+            stream,
+            payload_size,
+            local_size,
+            usable_size,
+            |page_number| self.read_page(page_number),
+        )
+    }
+
+    /// Names of the tables shown by `.tables`. Internal tables like sqlite_sequence are
+    /// left out, although they can still be queried by name.
+    pub fn table_names(&self) -> Vec<&str> {
+        self.schemas
+            .iter()
+            .filter(|schema| schema.kind == "table" && !schema.name.starts_with("sqlite_"))
+            .map(|schema| schema.name.as_str())
+            .sorted()
+            .collect()
+    }
+
+    /// The CREATE statements shown by `.schema`, in the order of the schema table. With a
+    /// table, only the statements of the objects on it. Indexes sqlite creates for constraints
+    /// have no statement and are left out.
+    pub fn schema_sql(&self, table: Option<&str>) -> Vec<&str> {
+        self.schemas
+            .iter()
+            .filter(|schema| !schema.sql.is_empty())
+            .filter(|schema| {
+                table.is_none_or(|table| schema.table_name.eq_ignore_ascii_case(table))
+            })
+            .map(|schema| schema.sql.as_str())
+            .collect()
+    }
+
+    /// Names of the indexes shown by `.indexes`, grouped by their table. Tables and indexes
+    /// are sorted by name. With a table, only the indexes on it.
+    pub fn index_names(&self, table: Option<&str>) -> Vec<(&str, Vec<&str>)> {
+        self.schemas
+            .iter()
+            .filter(|schema| schema.kind == "index")
+            .filter(|schema| {
+                table.is_none_or(|table| schema.table_name.eq_ignore_ascii_case(table))
+            })
+            .map(|schema| (schema.table_name.as_str(), schema.name.as_str()))
+            .sorted()
+            .group_by(|(table, _)| *table)
+            .into_iter()
+            .map(|(table, indexes)| (table, indexes.map(|(_, index)| index).collect()))
+            .collect()
+    }
+
+    /// Number of pages read so far, whether from the file or the page cache.
+    pub fn page_reads(&self) -> usize {
+        self.page_reads.get()
+    }
+
+    /// Number of pages read from the file so far, those that weren't in the page cache.
+    pub fn disk_reads(&self) -> usize {
+        self.disk_reads.get()
+    }
+
+    /// Take a shared lock on the database file, so processes writing with locks wait
+    /// until it is released and reads see a consistent file.
+    /// The lock is held until `unlock` is called or the DB is dropped.
+    /// Cached pages are dropped, as the file may have been written since they were read.
+    pub fn lock_shared(&self) -> Result<()> {
+        self.file.lock_shared()?;
+        self.page_cache.borrow_mut().clear();
+        Ok(())
+    }
+
+    /// Release a lock taken with `lock_shared`.
+    pub fn unlock(&self) -> Result<()> {
+        self.file.unlock()?;
+        Ok(())
+    }
+
+    /// Read the database header at the start of the file.
+    pub fn read_header(&self) -> Result<DatabaseHeader> {
+        let mut buffer = [0; 100];
+        self.file.read_exact_at(&mut buffer, 0)?;
+        DatabaseHeader::parse(&buffer)
+    }
+
+    /// Total number of pages in the database file.
+    pub fn page_count(&self) -> Result<u64> {
+        Ok(self.file.metadata()?.len() / self.page_size as u64)
+    }
+
+    /// Get the records of the given sorted rowids in a single traversal of the table btree,
+    /// so interior pages shared by several rows are read once. Rowids without a row are skipped.
+    pub fn get_records_by_row_ids(
+        &self,
+        sorted_ids: &[usize],
+        root_page: usize,
+    ) -> Result<Vec<Record>> {
+        let mut records = Vec::with_capacity(sorted_ids.len());
+        self.collect_records_by_row_ids(root_page, sorted_ids, &mut records, &mut HashSet::new())?;
+        Ok(records)
+    }
+
+    fn collect_records_by_row_ids(
+        &self,
+        page_number: usize,
+        sorted_ids: &[usize],
+        records: &mut Vec<Record>,
+        visited: &mut HashSet<usize>,
+    ) -> Result<()> {
+        visit_page(visited, page_number)?;
+        let page = self.read_page(page_number)?;
+        let page_header = get_page_header(&page)?;
+        let cell_pointers =
+            parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells);
+
+        match page_header.page_type {
+            BTreePage::InteriorTable => {
+                let mut remaining = sorted_ids;
+                for cell_pointer in cell_pointers {
+                    if remaining.is_empty() {
+                        return Ok(());
+                    }
+                    let cell = cell_at(&page, cell_pointer)?;
+                    let left_child_pointer = left_child_pointer(cell)?;
+                    // The key is the largest rowid in the left child
+                    let (key, _) = parse_varint(&cell[4..]);
+                    let (left_ids, rest) =
+                        remaining.split_at(remaining.partition_point(|row_id| *row_id <= key));
+                    if !left_ids.is_empty() {
+                        self.collect_records_by_row_ids(
+                            left_child_pointer,
+                            left_ids,
+                            records,
+                            visited,
+                        )?;
+                    }
+                    remaining = rest;
+                }
+
+                if !remaining.is_empty() {
+                    let right_most_pointer = page_header
+                        .right_most_pointer
+                        .ok_or_else(|| anyhow!("Interior page without right most pointer"))?;
+                    self.collect_records_by_row_ids(
+                        right_most_pointer as usize,
+                        remaining,
+                        records,
+                        visited,
+                    )?;
+                }
+            }
+            BTreePage::LeafTable => {
+                for cell_pointer in cell_pointers {
+                    let stream = cell_at(&page, cell_pointer)?;
+                    let (_payload_size, offset) = parse_varint(stream);
+                    let (row_id, _) = parse_varint(&stream[offset..]);
+                    if sorted_ids.binary_search(&row_id).is_ok() {
+                        records.push(self.parse_leaf_table_cell(stream)?);
+                    }
+                }
+            }
+            page_type => bail!("Expected a table page, found {:?}", page_type),
+        }
+
+        Ok(())
+    }
+
+    /// Get a single record by row_id. Does a btree traversal.
+    pub fn get_record_by_row_id(&self, row_id: u64, root_page: usize) -> Result<Record> {
+        let mut page_number = root_page;
+        let mut visited = HashSet::new();
+        loop {
+            visit_page(&mut visited, page_number)?;
+            // Start index of the page
+            let page = self.read_page(page_number)?;
+            let page_header = get_page_header(&page)?;
+
+            // Get all the cell pointers
+            let cell_pointers =
+                parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells);
+
+            match page_header.page_type {
+                // The content of the cell pointers are pointers to the left pages
+                BTreePage::InteriorTable => {
+                    let mut child = None;
+                    for cell_pointer in cell_pointers {
+                        let cell = cell_at(&page, cell_pointer)?;
+                        let left_child_pointer = left_child_pointer(cell)?;
+                        let (key, _offset) = parse_varint(&cell[4..]);
+                        // The key is the largest rowid in the left child, so an equal rowid is
+                        // there too
+                        if row_id <= (key as u64) {
+                            child = Some(left_child_pointer);
+                            break;
+                        }
+                    }
+
+                    page_number = match child {
+                        Some(child) => child,
+                        None => page_header
+                            .right_most_pointer
+                            .ok_or_else(|| anyhow!("Interior page without right most pointer"))?
+                            as usize,
+                    };
+                }
+                // If it is a leaf page. get the records directly
+                BTreePage::LeafTable => {
+                    for cell_pointer in cell_pointers.into_iter() {
+                        let stream = cell_at(&page, cell_pointer)?;
+                        let (_payload_size, offset) = parse_varint(stream); // total number of bytes of payload
+                        let (key, _) = parse_varint(&stream[offset..]); // integer key (rowid).
+
+                        if (key as u64) != row_id {
+                            continue;
+                        }
+
+                        return self.parse_leaf_table_cell(stream);
+                    }
+                    bail!("No row with rowid {}", row_id);
+                }
+                page_type => bail!("Expected a table page, found {:?}", page_type),
+            }
+        }
+    }
+
+    /// The schema row of a table.
+    fn table_schema(&self, table: &str) -> Result<&Schema> {
+        self.schemas
+            .iter()
+            .find(|schema| schema.kind == "table" && schema.table_name.eq_ignore_ascii_case(table))
+            .ok_or_else(|| anyhow!("no such table: {}", table))
+    }
+
+    /// Get fields in a table indexed by the the field name.
+    fn get_fields_in_table(&self, tablename: &str) -> Result<HashMap<String, (usize, Field)>> {
+        let schema = self.table_schema(tablename)?;
+        let (_, create_statement) = parse_creation(schema.sql.as_bytes())
+            .map_err(|_| anyhow!("Failed to parse schema of {}", tablename))?;
+
+        Ok(create_statement
+            .fields
+            .into_iter()
+            .enumerate()
+            .map(|(ind, field)| (field.name.clone(), (ind, field)))
+            .collect())
+    }
+
+    /// Scan the records of the table btree rooted at `page_number` with a rowid of at least
+    /// `first_row_id`, in rowid order.
+    fn scan_table(&self, page_number: usize, first_row_id: i64) -> TableScan<'_> {
+        TableScan {
+            db: self,
+            first_row_id,
+            pages: vec![page_number],
+            visited: HashSet::new(),
+            leaf: None,
+        }
+    }
+
+    /// Count the entries of the btree rooted at `page_number` from the number of cells of its
+    /// pages, without parsing any cell. Every cell of an index btree is an entry, a table btree
+    /// only has entries in its leaves.
+    fn count_rows(&self, page_number: usize) -> Result<usize> {
+        let mut count = 0;
+        let mut pages = vec![page_number];
+        let mut visited = HashSet::new();
+
+        while let Some(page_number) = pages.pop() {
+            visit_page(&mut visited, page_number)?;
+            let page = self.read_page(page_number)?;
+            let page_header = get_page_header(&page)?;
+            let cells = page_header.number_of_cells as usize;
+            // The entries of the page and whether it has children
+            let (entries, interior) = match page_header.page_type {
+                BTreePage::InteriorTable => (0, true),
+                BTreePage::InteriorIndex => (cells, true),
+                BTreePage::LeafTable | BTreePage::LeafIndex => (cells, false),
+            };
+            count += entries;
+            if interior {
+                let right_most_pointer = page_header
+                    .right_most_pointer
+                    .ok_or_else(|| anyhow!("Interior page without right most pointer"))?;
+                pages.push(right_most_pointer as usize);
+                let cell_pointers =
+                    parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells);
+                for cell_pointer in cell_pointers {
+                    pages.push(left_child_pointer(cell_at(&page, cell_pointer)?)?);
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Get the rows of a WITHOUT ROWID table. They are the records of an index btree in
+    /// primary key order, which hold the primary key columns first and then the other columns.
+    /// The values are put back in the order the columns are declared. The rows have no rowid,
+    /// their `row_id` is 0.
+    fn get_without_rowid_records(
+        &self,
+        root_page: usize,
+        create_statement: &CreateTableStatement,
+    ) -> Result<Vec<Record>> {
+        let mut entries = vec![];
+        let all = (Bound::Unbounded, Bound::Unbounded);
+        self.parse_index_page(root_page, &mut entries, all, &[false], &mut HashSet::new())?;
+
+        // The declared position of each value of a stored record
+        let fields = &create_statement.fields;
+        let key_positions = create_statement.primary_key.iter().filter_map(|column| {
+            fields
+                .iter()
+                .position(|field| field.name.eq_ignore_ascii_case(column))
+        });
+        let stored_order: Vec<usize> = key_positions.chain(0..fields.len()).unique().collect();
+
+        Ok(entries
+            .into_iter()
+            .map(|values| {
+                let mut columns = vec![Value::Null; fields.len()];
+                for (ind, value) in stored_order.iter().zip(values) {
+                    columns[*ind] = value;
+                }
+                Record { row_id: 0, columns }
+            })
+            .collect())
+    }
+
+    // Get all records from the index page whose keys are from `low` to `high`
+    // The keys of a descending column are in reverse order, which flips which subtrees to skip.
+    fn parse_index_page(
+        &self,
+        page_number: usize,
+        row_collector: &mut Vec<Vec<Value>>,
+        (low, high): (Bound<&[Value]>, Bound<&[Value]>),
+        descending: &[bool],
+        visited: &mut HashSet<usize>,
+    ) -> Result<()> {
+        visit_page(visited, page_number)?;
+        let page = self.read_page(page_number)?;
+        // Get the index page
+        let page_header = get_page_header(&page)?;
+
+        // Where a key is relative to a bound in the order of the index, comparing the columns
+        // the bound has values for
+        let index_order = |key: &[Value], bound: &[Value]| {
+            key.iter()
+                .zip(bound)
+                .zip(descending)
+                .map(|((key, bound), &descending)| {
+                    let ordering = key.compare(bound);
+                    if descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        };
+        // The bounds the range starts and ends with in the order of the index. Only the first
+        // column can have different bounds.
+        let (first, last) = if descending.first() == Some(&true) {
+            (high, low)
+        } else {
+            (low, high)
+        };
+        let before_range = |key: &[Value]| match first {
+            Bound::Included(bound) => index_order(key, bound).is_lt(),
+            Bound::Excluded(bound) => index_order(key, bound).is_le(),
+            Bound::Unbounded => false,
+        };
+        let after_range = |key: &[Value]| match last {
+            Bound::Included(bound) => index_order(key, bound).is_gt(),
+            Bound::Excluded(bound) => index_order(key, bound).is_ge(),
+            Bound::Unbounded => false,
+        };
+
+        if page_header.page_type == BTreePage::InteriorIndex {
+            let cell_pointers =
+        // get the index cell pointers
+        parse_cell_pointers(&page[12..], page_header.number_of_cells);
+
+            for cell_pointer in cell_pointers {
+                let cell = cell_at(&page, cell_pointer)?;
+                let left_child_pointer = left_child_pointer(cell)?;
+                let record = self.parse_index_cell_payload(&cell[4..])?;
+
+                // If cur_key comes before the range no need to check left tree
+                if before_range(&record) {
+                    continue;
+                }
+
+                // The left tree holds the keys up to cur_key, so visit it first to keep index order.
+                self.parse_index_page(
+                    left_child_pointer,
+                    row_collector,
+                    (low, high),
+                    descending,
+                    visited,
+                )?;
+
+                // if cur_key comes after the range. The remaining cells and the right most
+                // pointer only hold later keys.
+                if after_range(&record) {
+                    return Ok(());
+                }
+
+                // cur_key is in the range, the cell itself matches as well.
+                row_collector.push(record);
+            }
+
+            let right_most_pointer = page_header
+                .right_most_pointer
+                .ok_or_else(|| anyhow!("Interior page without right most pointer"))?;
+            return self.parse_index_page(
+                right_most_pointer as usize,
+                row_collector,
+                (low, high),
+                descending,
+                visited,
+            );
+        }
+        if page_header.page_type == BTreePage::LeafIndex {
+            let cell_pointers = parse_cell_pointers(&page[8..], page_header.number_of_cells);
+
+            for cell_pointer in cell_pointers {
+                let key_record = self.parse_index_cell_payload(cell_at(&page, cell_pointer)?)?;
+
+                if !before_range(&key_record) && !after_range(&key_record) {
+                    row_collector.push(key_record);
+                }
+            }
+            return Ok(());
+        }
+
+        bail!("Expected an index page, found {:?}", page_header.page_type)
+    }
+}
+
+/// Iterator over the records of a table btree in rowid order. Pages are read as the scan
+/// reaches them, so only the leaf page being walked is held in memory.
+/// Subtrees holding only rowids before `first_row_id` are never read.
+pub struct TableScan<'a> {
+    db: &'a DB,
+    first_row_id: i64,
+    pages: Vec<usize>, // pages still to visit, the next one last
+    visited: HashSet<usize>,
+    leaf: Option<(Vec<u8>, vec::IntoIter<u16>)>, // the leaf page and its cells still to read
+}
+
+impl TableScan<'_> {
+    /// Visit a page: the children of an interior page are queued, a leaf page is walked next.
+    fn visit(&mut self, page_number: usize) -> Result<()> {
+        visit_page(&mut self.visited, page_number)?;
+        let page = self.db.read_page(page_number)?;
+        let page_header = get_page_header(&page)?;
+        let cell_pointers =
+            parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells);
+
+        match page_header.page_type {
+            // The children are pushed in reverse, so the left most page is visited first and
+            // the right most pointer last
+            BTreePage::InteriorTable => {
+                let right_most_pointer = page_header
+                    .right_most_pointer
+                    .ok_or_else(|| anyhow!("Interior page without right most pointer"))?;
+                self.pages.push(right_most_pointer as usize);
+                for cell_pointer in cell_pointers.into_iter().rev() {
+                    let cell = cell_at(&page, cell_pointer)?;
+                    let left_child_pointer = left_child_pointer(cell)?;
+                    // The key is the largest rowid in the left child
+                    let (key, _) = parse_varint(&cell[4..]);
+                    if (key as i64) < self.first_row_id {
+                        break;
+                    }
+                    self.pages.push(left_child_pointer);
+                }
+            }
+            BTreePage::LeafTable => self.leaf = Some((page, cell_pointers.into_iter())),
+            // WITHOUT ROWID tables are index btrees, read by get_without_rowid_records
+            page_type => bail!("Expected a table page, found {:?}", page_type),
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for TableScan<'_> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((page, cell_pointers)) = &mut self.leaf {
+                match cell_pointers.next() {
+                    Some(cell_pointer) => {
+                        let record = cell_at(page, cell_pointer)
+                            .and_then(|cell| self.db.parse_leaf_table_cell(cell));
+                        match record {
+                            Ok(record) if record.row_id < self.first_row_id => continue,
+                            Ok(record) => return Some(Ok(record)),
+                            Err(err) => {
+                                // Nothing more is read after an error
+                                self.leaf = None;
+                                self.pages.clear();
+                                return Some(Err(err));
+                            }
+                        }
+                    }
+                    None => self.leaf = None,
+                }
+            }
+
+            let page_number = self.pages.pop()?;
+            if let Err(err) = self.visit(page_number) {
+                self.pages.clear();
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::record::parse_be_twos_complement;
+    use crate::select_sql::{parse_sql, parse_statement};
+
+    pub(crate) fn fixture_path(name: &str) -> String {
+        format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    pub(crate) fn open_fixture(name: &str) -> DB {
+        DB::open(fixture_path(name)).unwrap()
+    }
+
+    /// The rows of a query with each value as printed, NULL as an empty string.
+    fn texts(rows: Vec<Vec<Value>>) -> Vec<Vec<String>> {
+        rows.into_iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| value.to_text().unwrap_or_default())
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub(crate) fn query(db: &DB, sql: &str) -> Vec<Vec<String>> {
+        texts(db.execute_query(parse_sql(sql).unwrap()).unwrap().rows)
+    }
+
+    pub(crate) fn columns(db: &DB, sql: &str) -> Vec<String> {
+        db.execute_query(parse_sql(sql).unwrap()).unwrap().columns
+    }
+
+    pub(crate) fn pragma(db: &DB, sql: &str) -> Vec<Vec<String>> {
+        match parse_statement(sql).unwrap() {
+            Statement::Pragma(pragma) => texts(db.execute_pragma(&pragma).unwrap()),
+            statement => panic!("not a pragma: {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_limit_on_plain_aggregate() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies"),
+            vec![vec!["1000"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies LIMIT 5"),
+            vec![vec!["1000"]]
+        );
+        assert!(query(&db, "SELECT COUNT(*) FROM companies LIMIT 0").is_empty());
+    }
+
+    #[test]
+    fn test_count_all_rows_from_cell_counts() {
+        let db = open_fixture("deep_table.db");
+        assert_eq!(query(&db, "SELECT COUNT(*) FROM pages"), vec![vec!["300"]]);
+
+        let db = open_fixture("overflow.db");
+        let reads = db.page_reads();
+        assert_eq!(query(&db, "SELECT COUNT(*) FROM docs"), vec![vec!["4"]]);
+        let count_reads = db.page_reads() - reads;
+        assert_eq!(query(&db, "SELECT COUNT(id) FROM docs"), vec![vec!["4"]]);
+        // The overflow pages of the rows are never read
+        assert!(count_reads < db.page_reads() - reads - count_reads);
+
+        // Entries of interior index pages are rows too
+        let db = open_fixture("without_rowid.db");
+        assert_eq!(
+            query(&db, "SELECT count(*), COUNT(*) AS n FROM stock"),
+            vec![vec!["450", "450"]]
+        );
+        assert_eq!(
+            columns(&db, "SELECT count(*), COUNT(*) AS n FROM stock"),
+            vec!["count(*)", "n"]
+        );
+        assert_eq!(query(&db, "SELECT COUNT(*) FROM codes"), vec![vec!["3"]]);
+    }
+
+    #[test]
+    fn test_count_distinct_per_group() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country, COUNT(DISTINCT industry) FROM companies GROUP BY country"
+            ),
+            vec![
+                vec!["brazil", "3"],
+                vec!["canada", "2"],
+                vec!["france", "1"],
+                vec!["germany", "5"],
+                vec!["india", "4"],
+                vec!["japan", "2"],
+                vec!["uk", "3"],
+                vec!["usa", "1"],
+            ]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(DISTINCT industry), COUNT(country) FROM companies"
+            ),
+            vec![vec!["5", "1000"]]
+        );
+    }
+
+    #[test]
+    fn test_select_distinct() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT DISTINCT industry FROM companies WHERE country = 'canada'"
+            ),
+            vec![vec!["software"], vec!["retail"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT DISTINCT country, industry FROM companies LIMIT 3"
+            )
+            .len(),
+            3
+        );
+        // Rows keep the order they are first seen in, and the window applies to the deduped rows
+        assert_eq!(
+            query(
+                &db,
+                "SELECT DISTINCT country FROM companies WHERE industry = 'retail' LIMIT 3 OFFSET 1"
+            ),
+            vec![vec!["japan"], vec!["germany"], vec!["uk"]]
+        );
+        // Every group has a distinct country so DISTINCT keeps all of them
+        assert_eq!(
+            query(
+                &db,
+                "SELECT DISTINCT country, COUNT(*) FROM companies GROUP BY country"
+            )
+            .len(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_ungrouped_columns() {
+        let mut db = open_fixture("companies.db");
+        let sql = "SELECT country, industry, COUNT(*) FROM companies GROUP BY country";
+        assert_eq!(query(&db, sql).len(), 8);
+
+        db.strict = true;
+        let err = db.execute_query(parse_sql(sql).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column industry must appear in the GROUP BY clause or be used in an aggregate function"
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT Country, companies.industry, COUNT(*), MAX(id) FROM companies GROUP BY country, industry LIMIT 1"
+            ),
+            vec![vec!["brazil", "banking", "38", "977"]]
+        );
+
+        let mut db = open_fixture("library.db");
+        db.strict = true;
+        assert_eq!(
+            query(
+                &db,
+                "SELECT authors.name, COUNT(*) FROM authors JOIN books ON authors.id = books.author_id GROUP BY name ORDER BY authors.name LIMIT 1"
+            ),
+            vec![vec!["Ernest Hemingway", "2"]]
+        );
+        let sql = "SELECT title, COUNT(*) FROM authors JOIN books ON authors.id = books.author_id GROUP BY name";
+        assert!(db.execute_query(parse_sql(sql).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_unknown_functions_are_rejected() {
+        let mut db = open_fixture("companies.db");
+        for strict in [false, true] {
+            db.strict = strict;
+            for (sql, name) in [
+                ("SELECT GROUP_CONCAT(name) FROM companies", "GROUP_CONCAT"),
+                ("SELECT upper(name) FROM companies LIMIT 1", "upper"),
+                ("SELECT id FROM companies ORDER BY lower(name)", "lower"),
+            ] {
+                let err = db.execute_query(parse_sql(sql).unwrap()).unwrap_err();
+                assert_eq!(err.to_string(), format!("no such function: {}", name));
+            }
+            // Scalar functions aren't aggregates
+            for (sql, name) in [
+                ("SELECT printf(*) FROM companies", "printf"),
+                ("SELECT abs(*) FROM companies", "abs"),
+                (
+                    "SELECT json_extract(DISTINCT name) FROM companies",
+                    "json_extract",
+                ),
+            ] {
+                let err = db.execute_query(parse_sql(sql).unwrap()).unwrap_err();
+                assert_eq!(
+                    err.to_string(),
+                    format!(
+                        "{}() is not an aggregate function, it can't take * or DISTINCT",
+                        name
+                    )
+                );
+            }
+            assert_eq!(
+                query(&db, "SELECT abs(id) FROM companies LIMIT 2"),
+                vec![vec!["1"], vec!["2"]]
+            );
+            assert_eq!(
+                query(&db, "SELECT COUNT(*) FROM companies"),
+                vec![vec!["1000"]]
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_page_header_of_first_page() {
+        let db = open_fixture("companies.db");
+        let header = db.read_page_header(1).unwrap();
+
+        // The schema table holds the table, sqlite_sequence and the index
+        assert_eq!(header.page_type, BTreePage::LeafTable);
+        assert_eq!(header.number_of_cells, 3);
+        assert_eq!(header.right_most_pointer, None);
+        assert_eq!(header.size(), 8);
+
+        let header = db.read_page_header(2).unwrap();
+        assert_eq!(header.page_type, BTreePage::InteriorTable);
+        assert!(header.right_most_pointer.is_some());
+    }
+
+    #[test]
+    fn test_page_walk_skips_ptrmap_pages() {
+        let db = open_fixture("auto_vacuum.db");
+        let mut ptrmap_pages = vec![];
+        let mut root_pages = vec![];
+        for page_number in 1..=db.page_count().unwrap() as usize {
+            if db.is_ptrmap_page(page_number).unwrap() {
+                ptrmap_pages.push(page_number);
+                root_pages.extend(
+                    db.read_ptrmap_page(page_number)
+                        .unwrap()
+                        .into_iter()
+                        .filter(|entry| entry.page_type == ptrmap::PtrmapType::RootPage)
+                        .map(|entry| entry.page_number),
+                );
+            }
+        }
+        assert_eq!(ptrmap_pages, vec![2, 105, 208, 311, 414]);
+        assert_eq!(root_pages, vec![3, 4]);
+
+        assert_eq!(
+            db.read_page_header(2).unwrap_err().to_string(),
+            "Page 2 is a pointer map page, not a btree page"
+        );
+        assert!(db.read_page_header(3).is_ok());
+        assert_eq!(query(&db, "SELECT COUNT(*) FROM notes"), vec![vec!["300"]]);
+
+        // Without auto_vacuum page 2 is an ordinary page
+        let db = open_fixture("companies.db");
+        assert!(!db.is_ptrmap_page(2).unwrap());
+    }
+
+    #[test]
+    fn test_like_with_escape() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT name FROM companies WHERE name LIKE 'COMPANY 99_'"
+            ),
+            vec![
+                vec!["company 990"],
+                vec!["company 991"],
+                vec!["company 992"],
+                vec!["company 993"],
+                vec!["company 994"],
+                vec!["company 995"],
+                vec!["company 996"],
+                vec!["company 997"],
+                vec!["company 998"],
+                vec!["company 999"]
+            ]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM companies WHERE name LIKE 'company 1!%' ESCAPE '!'"
+            ),
+            vec![vec!["0"]]
+        );
+
+        // Integers are matched by their text
+        let library = open_fixture("library.db");
+        assert_eq!(
+            query(
+                &library,
+                "SELECT title FROM books WHERE title LIKE 'the%' AND year LIKE '19__'"
+            ),
+            vec![vec!["The Sun Also Rises"], vec!["The Old Man and the Sea"]]
+        );
+
+        let err = db
+            .execute_query(
+                parse_sql("SELECT name FROM companies WHERE name LIKE 'a%' ESCAPE 'ab'").unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ESCAPE expression must be a single character"
+        );
+    }
+
+    #[test]
+    fn test_offset_on_index_scan_skips_fetching_records() {
+        let db = open_fixture("companies.db");
+
+        let all = query(&db, "SELECT id, name FROM companies WHERE country = 'usa'");
+        assert_eq!(all.len(), 192);
+        let full_reads = db.page_reads();
+
+        let page = query(
+            &db,
+            "SELECT id, name FROM companies WHERE country = 'usa' LIMIT 1 OFFSET 100",
+        );
+        assert_eq!(page, vec![all[100].clone()]);
+
+        // Only the index pages and the path to a single record are read
+        let window_reads = db.page_reads() - full_reads;
+        assert!(window_reads < 10, "read {} pages", window_reads);
+        assert!(
+            window_reads < full_reads,
+            "{} >= {}",
+            window_reads,
+            full_reads
+        );
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT name FROM companies WHERE country = 'usa' LIMIT 2 OFFSET 191"
+            ),
+            vec![all[191][1..].to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_rowid_keyset_pagination() {
+        let db = open_fixture("companies.db");
+
+        let page = query(
+            &db,
+            "SELECT id, name FROM companies WHERE rowid > 990 ORDER BY rowid LIMIT 3",
+        );
+        assert_eq!(
+            page,
+            vec![
+                vec!["991", "company 991"],
+                vec!["992", "company 992"],
+                vec!["993", "company 993"],
+            ]
+        );
+        // Only the path down to the last leaf is read, not the earlier pages of the table
+        assert!(db.page_reads() <= 3, "read {} pages", db.page_reads());
+
+        // The INTEGER PRIMARY KEY works as the rowid too
+        assert_eq!(
+            query(&db, "SELECT id FROM companies WHERE id >= 998"),
+            vec![vec!["998"], vec!["999"], vec!["1000"]]
+        );
+    }
+
+    #[test]
+    fn test_keyword_column_names() {
+        let db = open_fixture("keywords.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT \"order\", count FROM items WHERE \"group\" = 'a' ORDER BY \"order\""
+            ),
+            vec![vec!["6", "16"], vec!["8", "4"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT count, order FROM items WHERE count > 4 ORDER BY count DESC LIMIT 2"
+            ),
+            vec![vec!["25", "5"], vec!["16", "6"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT \"group\", COUNT(*) FROM items GROUP BY \"group\""
+            ),
+            vec![vec!["a", "2"], vec!["b", "3"]]
+        );
+    }
+
+    #[test]
+    fn test_in_list() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(&db, "SELECT id FROM companies WHERE id IN (3, 1, 'x', 5)"),
+            vec![vec!["1"], vec!["3"], vec!["5"]]
+        );
+        // An empty list is always false
+        assert!(query(&db, "SELECT id FROM companies WHERE country IN ()").is_empty());
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies WHERE country IN ()"),
+            vec![vec!["0"]]
+        );
+
+        // country is indexed, each value is looked up and a repeated value gives its rows once
+        let indexed = query(
+            &db,
+            "SELECT id FROM companies WHERE country IN ('uk', 'usa', 'uk') LIMIT 200",
+        );
+        let scanned = query(
+            &db,
+            "SELECT id FROM companies WHERE country LIKE 'uk' OR country LIKE 'usa' LIMIT 200",
+        );
+        assert_eq!(indexed.len(), 200);
+        assert_eq!(indexed, scanned);
+    }
+
+    #[test]
+    fn test_records_at_the_overflow_boundary() {
+        let db = open_fixture("overflow.db");
+        let body = |row_id: usize, length: usize| -> String {
+            (0..length)
+                .map(|j| (b'a' + ((row_id + j) % 26) as u8) as char)
+                .collect()
+        };
+
+        // Payloads of 988 and 989 bytes stay in the cell, 990 bytes and more overflow
+        let rows = query(&db, "SELECT id, body FROM docs");
+        let expected: Vec<Vec<String>> = [984, 985, 986, 5000]
+            .into_iter()
+            .enumerate()
+            .map(|(i, length)| vec![(i + 1).to_string(), body(i + 1, length)])
+            .collect();
+        assert_eq!(rows, expected);
+
+        // The ranged rowid scan reads the same record
+        assert_eq!(
+            query(&db, "SELECT body FROM docs WHERE id >= 4"),
+            vec![vec![body(4, 5000)]]
+        );
+    }
+
+    #[test]
+    fn test_schema_spanning_multiple_pages() {
+        let db = open_fixture("many_tables.db");
+        assert_eq!(
+            db.read_page_header(1).unwrap().page_type,
+            BTreePage::InteriorTable
+        );
+
+        let names = db.table_names();
+        assert_eq!(names.len(), 40);
+        assert_eq!(names[0], "table_aa");
+        assert_eq!(names[39], "table_bn");
+        assert_eq!(
+            query(&db, "SELECT label FROM table_bn"),
+            vec![vec!["row of table_bn"]]
+        );
+    }
+
+    #[test]
+    fn test_schema_sql() {
+        let db = open_fixture("library.db");
+        assert_eq!(
+            db.schema_sql(Some("Books")),
+            vec![
+                "CREATE TABLE books (id integer primary key, title text, author_id integer, year integer)",
+                "CREATE INDEX idx_books_author_id ON books (author_id)",
+            ]
+        );
+        assert_eq!(db.schema_sql(None).len(), 3);
+        assert!(db.schema_sql(Some("missing")).is_empty());
+
+        // Including views and triggers
+        let db = open_fixture("integer_sizes.db");
+        assert_eq!(db.schema_sql(None).len(), 3);
+    }
+
+    #[test]
+    fn test_index_names() {
+        let db = open_fixture("text_pk.db");
+        assert_eq!(
+            db.index_names(None),
+            vec![
+                ("countries", vec!["sqlite_autoindex_countries_1"]),
+                ("sizes", vec!["sqlite_autoindex_sizes_1"])
+            ]
+        );
+        assert_eq!(
+            db.index_names(Some("SIZES")),
+            vec![("sizes", vec!["sqlite_autoindex_sizes_1"])]
+        );
+        assert!(db.index_names(Some("missing")).is_empty());
+        assert!(open_fixture("readings.db").index_names(None).is_empty());
+    }
+
+    #[test]
+    fn test_schema_row_on_overflow_pages() {
+        let db = open_fixture("wide_schema.db");
+
+        let schema = db
+            .schemas
+            .iter()
+            .find(|schema| schema.name == "wide")
+            .unwrap();
+        assert!(schema.sql.ends_with("column_ch text)"));
+        assert_eq!(
+            query(&db, "SELECT column_aa, column_ch FROM wide"),
+            vec![vec!["first", "last"], vec!["one", "fifty-nine"]]
+        );
+    }
+
+    #[test]
+    fn test_without_rowid_table() {
+        let db = open_fixture("without_rowid.db");
+
+        assert_eq!(
+            query(&db, "SELECT * FROM stock WHERE sku = 'sku-100'"),
+            vec![
+                vec!["east", "34", "sku-100", "note 302"],
+                vec!["north", "0", "sku-100", ""],
+                vec!["south", "17", "sku-100", "note 301"],
+            ]
+        );
+        // Rows come in primary key order, the index on quantity isn't used
+        assert_eq!(
+            query(&db, "SELECT sku, warehouse FROM stock WHERE quantity = 3"),
+            vec![
+                vec!["sku-019", "east"],
+                vec!["sku-053", "north"],
+                vec!["sku-086", "south"],
+                vec!["sku-119", "east"],
+            ]
+        );
+        assert_eq!(query(&db, "SELECT COUNT(*) FROM stock"), vec![vec!["450"]]);
+        assert_eq!(
+            query(&db, "SELECT label, code FROM codes WHERE code > 2"),
+            vec![vec!["five", "5"], vec!["ten", "10"]]
+        );
+        assert!(db
+            .execute_query(parse_sql("SELECT rowid FROM codes").unwrap())
+            .is_err());
+        assert_eq!(
+            pragma(&db, "PRAGMA table_info(stock)"),
+            vec![
+                vec!["0", "warehouse", "TEXT", "1", "", "2"],
+                vec!["1", "quantity", "INTEGER", "0", "", "0"],
+                vec!["2", "sku", "TEXT", "1", "", "1"],
+                vec!["3", "note", "TEXT", "0", "", "0"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reserved_bytes_at_the_end_of_pages() {
+        let db = open_fixture("reserved_bytes.db");
+        assert_eq!(db.usable_size(), 992);
+
+        let rows = query(&db, "SELECT id, title, body FROM docs");
+        assert_eq!(rows.len(), 43);
+        let total = |column: usize| rows.iter().map(|row| row[column].len()).sum::<usize>();
+        assert_eq!((total(1), total(2)), (8145, 82435));
+        // Rows whose payload would fit locally in pages without reserved space
+        let last_letters: Vec<&str> = rows[40..]
+            .iter()
+            .map(|row| &row[2][row[2].len() - 5..])
+            .collect();
+        assert_eq!(last_letters, vec!["yzabc", "opqrs", "efghi"]);
+
+        // An index key on overflow pages
+        let title = &rows[16][1];
+        assert_eq!(
+            query(
+                &db,
+                &format!("SELECT id FROM docs WHERE title = '{}'", title)
+            ),
+            vec![vec!["17"]]
+        );
+    }
+
+    #[test]
+    fn test_truncated_interior_cell_is_an_error() {
+        // A copy of the fixture with the only cell of the root page moved to its last 2 bytes
+        let path = std::env::temp_dir().join(format!("truncated_cell_{}.db", std::process::id()));
+        let mut bytes = fs::read(fixture_path("deep_table.db")).unwrap();
+        bytes[1024 + 12..1024 + 14].copy_from_slice(&1022u16.to_be_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let db = DB::open(&path).unwrap();
+        assert!(db.scan_table(2, i64::MIN).next().unwrap().is_err());
+        assert!(db.get_record_by_row_id(1, 2).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_btree_cycle_is_an_error() {
+        // A copy of a fixture with the first child of an interior page pointing back at it
+        let cyclic_copy = |name: &str, page_size: usize, page_number: usize| {
+            let path = std::env::temp_dir().join(format!("cyclic_{}_{}", std::process::id(), name));
+            let mut bytes = fs::read(fixture_path(name)).unwrap();
+            let page = (page_number - 1) * page_size;
+            let header = if page_number == 1 { page + 100 } else { page };
+            let cell = page + u16::from_be_bytes([bytes[header + 12], bytes[header + 13]]) as usize;
+            bytes[cell..cell + 4].copy_from_slice(&(page_number as u32).to_be_bytes());
+            fs::write(&path, bytes).unwrap();
+            path
+        };
+
+        let path = cyclic_copy("deep_table.db", 1024, 2);
+        let db = DB::open(&path).unwrap();
+        for sql in [
+            "SELECT COUNT(*) FROM pages",
+            "SELECT COUNT(id) FROM pages",
+            "SELECT content FROM pages WHERE id = 1",
+            "SELECT content FROM pages WHERE id IN (1, 2)",
+        ] {
+            assert!(
+                db.execute_query(parse_sql(sql).unwrap()).is_err(),
+                "{}",
+                sql
+            );
+        }
+        assert!(db.get_record_by_row_id(1, 2).is_err());
+        fs::remove_file(&path).unwrap();
+
+        let path = cyclic_copy("companies.db", 4096, 4);
+        let db = DB::open(&path).unwrap();
+        let sql = "SELECT id FROM companies WHERE country = 'brazil'";
+        assert!(db.execute_query(parse_sql(sql).unwrap()).is_err());
+        fs::remove_file(&path).unwrap();
+
+        // The schema table spans several pages
+        let path = cyclic_copy("many_tables.db", 1024, 1);
+        assert!(DB::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unexpected_pages_are_errors() {
+        let db = open_fixture("companies.db");
+        let root_page = |name: &str| {
+            let schema = db.schemas.iter().find(|schema| schema.name == name);
+            schema.unwrap().root_page as usize
+        };
+        let (table_root, index_root) = (root_page("companies"), root_page("idx_companies_country"));
+
+        assert!(db.get_record_by_row_id(5, index_root).is_err());
+        assert!(db.get_record_by_row_id(5000, table_root).is_err());
+        assert!(db.scan_table(index_root, i64::MIN).next().unwrap().is_err());
+        let all = (Bound::Unbounded, Bound::Unbounded);
+        assert!(db
+            .parse_index_page(table_root, &mut vec![], all, &[false], &mut HashSet::new())
+            .is_err());
+
+        // Pages outside the file and cells outside their page
+        let page_count = db.page_count().unwrap() as usize;
+        assert!(db.read_page(0).is_err());
+        assert!(db.read_page(page_count + 1).is_err());
+        assert!(db.read_page(page_count).is_ok());
+        let page = db.read_page(table_root).unwrap();
+        assert!(
+            parse_btree_leaf_cell_content(5000, &page, db.usable_size(), |_| unreachable!())
+                .is_err()
+        );
+
+        let err = db
+            .execute_query(parse_sql("SELECT name FROM missing").unwrap())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no such table: missing");
+    }
+
+    #[test]
+    fn test_pending_writes_detects_wal() {
+        let dir = std::env::temp_dir().join(format!("pending_writes_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("companies.db");
+        fs::copy(fixture_path("companies.db"), &path).unwrap();
+        assert!(pending_writes(&path).is_empty());
+
+        // An empty WAL is left behind by closed connections and is fine
+        let wal = dir.join("companies.db-wal");
+        fs::write(&wal, b"").unwrap();
+        assert!(pending_writes(&path).is_empty());
+
+        fs::write(&wal, b"frames").unwrap();
+        let warnings = pending_writes(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("companies.db-wal"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn test_total_and_sum() {
+        let db = open_fixture("readings.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT SUM(calibration), TOTAL(calibration), SUM(value), TOTAL(value), SUM(id), TOTAL(id) FROM readings"
+            ),
+            vec![vec!["", "0.0", "7.75", "7.75", "15", "15.0"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT sensor, SUM(value), TOTAL(value) FROM readings GROUP BY sensor"
+            ),
+            vec![
+                vec!["east", "", "0.0"],
+                vec!["north", "1.5", "1.5"],
+                vec!["south", "6.25", "6.25"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_row_id_equal_to_interior_key() {
+        let db = open_fixture("companies.db");
+        let root_page = db.schemas[0].root_page as usize;
+        let page = db.read_page(root_page).unwrap();
+        let page_header = get_page_header(&page).unwrap();
+        assert_eq!(page_header.page_type, BTreePage::InteriorTable);
+
+        // Every divider key is the last rowid of its left child, the next one starts a new page
+        for cell_pointer in
+            parse_cell_pointers(&page[page_header.size()..], page_header.number_of_cells)
+        {
+            let (key, _) = parse_varint(&page[cell_pointer as usize + 4..]);
+            for row_id in [key, key + 1] {
+                let record = db.get_record_by_row_id(row_id as u64, root_page).unwrap();
+                assert_eq!(record.row_id, row_id as i64);
+                assert_eq!(
+                    record.columns[1],
+                    Value::Text(format!("company {}", row_id))
+                );
+            }
+
+            let records = db
+                .get_records_by_row_ids(&[key, key + 1], root_page)
+                .unwrap();
+            let row_ids: Vec<i64> = records.iter().map(|record| record.row_id).collect();
+            assert_eq!(row_ids, vec![key as i64, key as i64 + 1]);
+
+            assert_eq!(
+                query(
+                    &db,
+                    &format!("SELECT id FROM companies WHERE id >= {} LIMIT 2", key)
+                ),
+                vec![vec![key.to_string()], vec![(key + 1).to_string()]]
+            );
+        }
+    }
+
+    #[test]
+    fn test_negated_conditions() {
+        let db = open_fixture("companies.db");
+        let count = |condition: &str| {
+            query(
+                &db,
+                &format!("SELECT COUNT(*) FROM companies WHERE {}", condition),
+            )[0][0]
+                .clone()
+        };
+        assert_eq!(count("name NOT LIKE 'company 1%'"), "888");
+        assert_eq!(count("country NOT IN ('usa', 'brazil')"), "692");
+        assert_eq!(count("id BETWEEN 10 AND 20"), "11");
+        assert_eq!(count("id NOT BETWEEN 10 AND 990"), "19");
+        assert_eq!(count("name GLOB 'company 1?'"), "10");
+        assert_eq!(count("name NOT GLOB '*0'"), "900");
+
+        // Rows where the column is NULL match neither a condition nor its negation
+        let db = open_fixture("readings.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM readings WHERE value NOT BETWEEN 2 AND 5"
+            ),
+            vec![vec!["1"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM readings WHERE value NOT IN (4)"),
+            vec![vec!["1"], vec!["4"]]
+        );
+    }
+
+    #[test]
+    fn test_open_with_page_size() {
+        // A copy of the fixture with the page size in the header zeroed out
+        let path = std::env::temp_dir().join(format!("page_size_{}.db", std::process::id()));
+        let mut bytes = fs::read(fixture_path("companies.db")).unwrap();
+        bytes[16..18].copy_from_slice(&[0, 0]);
+        fs::write(&path, bytes).unwrap();
+
+        let db = DB::open_with_page_size(&path, 4096).unwrap();
+        assert_eq!(
+            query(&db, "SELECT id, name FROM companies WHERE id = 500"),
+            vec![vec!["500", "company 500"]]
+        );
+        for page_size in [0, 256, 1000] {
+            assert!(DB::open_with_page_size(&path, page_size).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open() {
+        let db = DB::open(fixture_path("reserved_bytes.db")).unwrap();
+        assert_eq!((db.page_size, db.reserved_bytes), (1024, 32));
+        assert_eq!(db.table_names(), vec!["docs"]);
+        assert_eq!(
+            query(&db, "SELECT id FROM docs WHERE id = 3"),
+            vec![vec!["3"]]
+        );
+
+        // Not a database, or no file at all
+        assert!(DB::open(fixture_path("generate.py")).is_err());
+        assert!(DB::open(fixture_path("missing.db")).is_err());
+    }
+
+    #[test]
+    fn test_index_lookup_of_overflowing_keys() {
+        let db = open_fixture("long_keys.db");
+        // Same titles as fixtures/generate.py
+        let title = |row_id: usize| {
+            let body: String = (0..40 + row_id * 97)
+                .map(|j| (b'a' + ((row_id + j) % 26) as u8) as char)
+                .collect();
+            format!("title {:02} {}", row_id, body)
+        };
+
+        for row_id in 1..=30 {
+            let sql = format!("SELECT id FROM notes WHERE title = '{}'", title(row_id));
+            assert_eq!(query(&db, &sql), vec![vec![row_id.to_string()]]);
+        }
+    }
+
+    #[test]
+    fn test_conditions_joined_by_and() {
+        let db = open_fixture("companies.db");
+
+        // The index finds the country, the industry still filters the rows before the window
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM companies WHERE country = 'india' AND industry = 'retail' LIMIT 2 OFFSET 1"
+            ),
+            vec![vec!["77"], vec!["109"]]
+        );
+        // The index condition doesn't have to come first
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM companies WHERE industry = 'retail' AND country = 'india'"
+            ),
+            vec![vec!["28"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM companies WHERE id > 100 AND country = 'france' LIMIT 2"
+            ),
+            vec![vec!["107"], vec!["115"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM companies WHERE year_founded BETWEEN 1950 AND 1960 AND industry = 'retail' AND employees > 2000"
+            ),
+            vec![vec!["10"]]
+        );
+    }
+
+    #[test]
+    fn test_conditions_joined_by_or() {
+        let db = open_fixture("companies.db");
+        let count = |condition: &str| {
+            query(
+                &db,
+                &format!("SELECT COUNT(*) FROM companies WHERE {}", condition),
+            )[0][0]
+                .clone()
+        };
+
+        assert_eq!(count("country = 'usa' OR country = 'canada'"), "307");
+        // AND binds tighter than OR
+        assert_eq!(
+            count("country = 'india' AND industry = 'retail' OR country = 'uk'"),
+            "144"
+        );
+        // The country outside the parentheses can still use the index
+        assert_eq!(
+            count("country = 'india' AND (industry = 'retail' OR industry = 'health')"),
+            "57"
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM companies WHERE country = 'uk' OR id < 3 ORDER BY id LIMIT 3"
+            ),
+            vec![vec!["1"], vec!["2"], vec!["6"]]
+        );
+    }
+
+    #[test]
+    fn test_sqlite_sequence() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(db.table_names(), vec!["companies"]);
+        assert_eq!(
+            query(
+                &db,
+                "SELECT seq FROM sqlite_sequence WHERE name = 'companies'"
+            ),
+            vec![vec!["1000"]]
+        );
+    }
+
+    #[test]
+    fn test_schema_qualified_table() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id, name FROM main.companies WHERE country = 'uk'"
+            ),
+            query(&db, "SELECT id, name FROM companies WHERE country = 'uk'")
+        );
+    }
+
+    #[test]
+    fn test_values_group_and_join_by_type() {
+        // Groups are in the order of their values, not of their text
+        let db = open_fixture("integer_sizes.db");
+        let groups = query(&db, "SELECT n FROM numbers GROUP BY n");
+        let numbers: Vec<i64> = groups.iter().map(|row| row[0].parse().unwrap()).collect();
+        assert!(
+            numbers.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            numbers
+        );
+        let db = open_fixture("companies.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT employees, COUNT(*) FROM companies GROUP BY employees LIMIT 3"
+            ),
+            vec![vec!["15", "1"], vec!["16", "1"], vec!["17", "1"]]
+        );
+
+        // The number 10 and the text '10' are different values, so are 9 and '9'
+        let db = open_fixture("mixed_types.db");
+        assert_eq!(
+            query(&db, "SELECT COUNT(DISTINCT reading) FROM mixed"),
+            vec![vec!["7"]]
+        );
+        assert_eq!(query(&db, "SELECT DISTINCT reading FROM mixed").len(), 8);
+        assert_eq!(
+            query(&db, "SELECT reading, COUNT(*) FROM mixed GROUP BY reading").len(),
+            8
+        );
+
+        // 1.0 joins 1, but not the text '1'
+        let db = open_fixture("join_keys.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT floats.id, floats.k, ints.id FROM floats JOIN ints ON floats.k = ints.k"
+            ),
+            vec![vec!["1", "1.0", "1"]]
+        );
+    }
+
+    #[test]
+    fn test_numeric_looking_text_compares_as_text() {
+        let db = open_fixture("text_numbers.db");
+
+        // Rows in rowid order: 50, 7, 9, 100, abc, 55, 3
+        let cases = [
+            ("v < '6'", vec!["50", "100", "55", "3"]),
+            ("v BETWEEN '1' AND '5'", vec!["100", "3"]),
+            ("v > '6'", vec!["7", "9", "abc"]),
+            ("v > 6", vec!["7", "9", "abc"]),
+            ("v = 50", vec!["50"]),
+            ("v IN (7, '9')", vec!["7", "9"]),
+            (
+                "v NOT BETWEEN '1' AND '5'",
+                vec!["50", "7", "9", "abc", "55"],
+            ),
+        ];
+        // The scan and the index lookup find the same rows
+        for table in ["plain", "indexed"] {
+            for (condition, expected) in &cases {
+                let sql = format!("SELECT v FROM {} WHERE {}", table, condition);
+                assert_eq!(
+                    query(&db, &sql),
+                    expected.iter().map(|v| vec![*v]).collect::<Vec<_>>(),
+                    "{}",
+                    sql
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_untyped_column_compares_values_as_they_are() {
+        let db = open_fixture("mixed_types.db");
+
+        // Rows: 10, '9', NULL, x'00ff', 2.5, '10', 9, 'abc'. Numbers sort before text and blobs.
+        let cases = [
+            ("reading = 10", vec!["1"]),
+            ("reading = '10'", vec!["6"]),
+            ("reading > 5", vec!["1", "2", "4", "6", "7", "8"]),
+            ("reading < '5'", vec!["1", "5", "6", "7"]),
+            ("reading IN (9, '10')", vec!["6", "7"]),
+            (
+                "reading BETWEEN 1 AND 'b'",
+                vec!["1", "2", "5", "6", "7", "8"],
+            ),
+        ];
+        for (condition, expected) in cases {
+            let sql = format!("SELECT id FROM mixed WHERE {}", condition);
+            assert_eq!(
+                query(&db, &sql),
+                expected.iter().map(|id| vec![*id]).collect::<Vec<_>>(),
+                "{}",
+                sql
+            );
+        }
+        // The rowid has INTEGER affinity
+        assert_eq!(
+            query(&db, "SELECT reading FROM mixed WHERE id = '5'"),
+            vec![vec!["2.5"]]
+        );
+    }
+
+    #[test]
+    fn test_window_of_index_range_lookup() {
+        let db = open_fixture("text_numbers.db");
+
+        // The window applies to the rows the condition matches, not to the index entries in
+        // the range
+        let cases = [
+            ("v < '6' LIMIT 2", vec!["50", "100"]),
+            ("v < '6' LIMIT 2 OFFSET 1", vec!["100", "55"]),
+            ("v BETWEEN '1' AND '5' LIMIT 1 OFFSET 1", vec!["3"]),
+            ("v > 6 LIMIT 2", vec!["7", "9"]),
+            ("v IN (7, '9') LIMIT 1 OFFSET 1", vec!["9"]),
+        ];
+        for table in ["plain", "indexed"] {
+            for (condition, expected) in &cases {
+                let sql = format!("SELECT v FROM {} WHERE {}", table, condition);
+                assert_eq!(
+                    query(&db, &sql),
+                    expected.iter().map(|v| vec![*v]).collect::<Vec<_>>(),
+                    "{}",
+                    sql
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_escaped_quotes() {
+        let db = open_fixture("quotes.db");
+
+        assert_eq!(
+            query(&db, "SELECT id, name FROM people WHERE name = 'O''Brien'"),
+            vec![vec!["1", "O'Brien"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM people WHERE name = 'it''''s'"),
+            vec![vec!["3"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM people WHERE name LIKE '%''%' AND name != ''''"
+            ),
+            vec![vec!["1"], vec!["3"]]
+        );
+    }
+
+    #[test]
+    fn test_qualified_columns() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT companies.id, Companies.NAME FROM companies WHERE companies.country = 'uk' ORDER BY companies.id DESC"
+            ),
+            query(
+                &db,
+                "SELECT id, name FROM companies WHERE country = 'uk' ORDER BY id DESC"
+            )
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT companies.country, COUNT(companies.id) FROM companies GROUP BY companies.country"
+            ),
+            query(
+                &db,
+                "SELECT country, COUNT(id) FROM companies GROUP BY country"
+            )
+        );
+        assert_eq!(
+            columns(&db, "SELECT companies.name FROM companies"),
+            vec!["name"]
+        );
+
+        let err = db
+            .execute_query(parse_sql("SELECT other.name FROM companies").unwrap())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no such column: other.name");
+    }
+
+    #[test]
+    fn test_primary_key_declared_second() {
+        let db = open_fixture("pk_second.db");
+
+        assert_eq!(
+            query(&db, "SELECT id, name, age FROM people WHERE age > 50"),
+            vec![vec!["20", "grace", "85"], vec!["30", "linus", "54"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT name FROM people WHERE id = 10"),
+            vec![vec!["ada"]]
+        );
+    }
+
+    #[test]
+    fn test_primary_key_that_is_not_the_rowid() {
+        let db = open_fixture("text_pk.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT rowid, code, name FROM countries WHERE code = 'jp'"
+            ),
+            vec![vec!["2", "jp", "Japan"]]
+        );
+        // Only INTEGER is the rowid, an INT key is stored in the record
+        assert_eq!(
+            query(&db, "SELECT n, label FROM sizes WHERE n > 10"),
+            vec![vec!["20", "medium"], vec!["30", "large"]]
+        );
+    }
+
+    #[test]
+    fn test_compare_full_scan() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM companies WHERE name < 'company 2'"
+            ),
+            vec![vec!["112"]]
+        );
+        // Numbers compare numerically, not as text
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM companies WHERE year_founded > 2000"
+            ),
+            vec![vec!["158"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies WHERE id <= 50"),
+            vec![vec!["50"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies WHERE country != 'usa'"),
+            vec![vec!["808"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies WHERE country <> 'usa'"),
+            vec![vec!["808"]]
+        );
+
+        // NULL compares as neither equal nor unequal
+        let db = open_fixture("readings.db");
+        assert_eq!(
+            query(&db, "SELECT id FROM readings WHERE value != 4"),
+            vec![vec!["1"], vec!["4"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM readings WHERE value < 3"),
+            vec![vec!["1"], vec!["4"]]
+        );
+    }
+
+    #[test]
+    fn test_index_lookup_outputs_each_row_once() {
+        let db = open_fixture("companies.db");
+
+        // The country index isn't unique, many entries share each key
+        let ids: Vec<String> = query(&db, "SELECT id FROM companies WHERE country = 'usa'")
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(ids.len(), 192);
+        assert_eq!(ids.iter().unique().count(), ids.len());
+
+        let scanned: Vec<String> = query(&db, "SELECT id FROM companies WHERE country LIKE 'usa'")
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect();
+        assert_eq!(ids, scanned);
+    }
+
+    #[test]
+    fn test_page_cache() {
+        let db = open_fixture("companies.db");
+        let root_page = db.schemas[0].root_page as usize;
+
+        // Lookups from the root share its interior pages, which are read from the file once
+        let reads = db.page_reads();
+        let disk_reads = db.disk_reads();
+        for row_id in [1, 2, 3, 300, 301, 999, 1000] {
+            db.get_record_by_row_id(row_id, root_page).unwrap();
+        }
+        let page_reads = db.page_reads() - reads;
+        let disk_reads = db.disk_reads() - disk_reads;
+        assert!(disk_reads < page_reads, "{} >= {}", disk_reads, page_reads);
+
+        // Running a query again only reads cached pages
+        let sql = "SELECT id, name FROM companies WHERE country = 'eritrea'";
+        let rows = query(&db, sql);
+        let disk_reads = db.disk_reads();
+        assert_eq!(query(&db, sql), rows);
+        assert_eq!(db.disk_reads(), disk_reads);
+
+        // Taking a lock drops the cache
+        db.lock_shared().unwrap();
+        assert_eq!(query(&db, sql), rows);
+        assert!(db.disk_reads() > disk_reads);
+        db.unlock().unwrap();
+    }
+
+    #[test]
+    fn test_get_records_by_row_ids() {
+        let db = open_fixture("companies.db");
+        let root_page = db.schemas[0].root_page as usize;
+        let row_ids = [1, 2, 300, 301, 999, 1000];
+
+        let reads = db.page_reads();
+        let one_by_one: Vec<Vec<Value>> = row_ids
+            .iter()
+            .map(|row_id| {
+                db.get_record_by_row_id(*row_id as u64, root_page)
+                    .unwrap()
+                    .columns
+            })
+            .collect();
+        let one_by_one_reads = db.page_reads() - reads;
+
+        let reads = db.page_reads();
+        let bulk: Vec<Vec<Value>> = db
+            .get_records_by_row_ids(&row_ids, root_page)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.columns)
+            .collect();
+        let bulk_reads = db.page_reads() - reads;
+
+        assert_eq!(bulk, one_by_one);
+        // The root is read once instead of once per row
+        assert!(
+            bulk_reads < one_by_one_reads,
+            "{} >= {}",
+            bulk_reads,
+            one_by_one_reads
+        );
+
+        // Missing rowids are skipped
+        assert_eq!(
+            db.get_records_by_row_ids(&[1, 5000], root_page)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_index_lookup_with_large_row_ids() {
+        let db = open_fixture("large_rowids.db");
+
+        assert_eq!(
+            query(&db, "SELECT id, code FROM items WHERE code = 'small'"),
+            vec![vec!["5", "small"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM items WHERE code = 'medium'"),
+            vec![vec!["9000000"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM items WHERE code = 'big'"),
+            vec![vec!["3000000000"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id, code FROM items WHERE code = 'filler 9'"),
+            vec![vec![(1u64 << 49).to_string(), "filler 9".to_owned()]]
+        );
+
+        assert_eq!(
+            parse_be_twos_complement(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]),
+            -2
+        );
+        assert_eq!(parse_be_twos_complement(&[0x7f; 8]), 0x7f7f7f7f7f7f7f7f);
+    }
+
+    #[test]
+    fn test_result_column_names() {
+        let db = open_fixture("companies.db");
+
+        let sql = "SELECT COUNT(*) AS total FROM companies";
+        assert_eq!(columns(&db, sql), vec!["total"]);
+        assert_eq!(query(&db, sql), vec![vec!["1000"]]);
+        let sql = "SELECT count(*), COUNT(DISTINCT country) FROM companies";
+        assert_eq!(
+            columns(&db, sql),
+            vec!["count(*)", "COUNT(DISTINCT country)"]
+        );
+        assert_eq!(query(&db, sql), vec![vec!["1000", "8"]]);
+        assert_eq!(
+            columns(&db, "SELECT id, name AS company FROM companies LIMIT 2"),
+            vec!["id", "company"]
+        );
+    }
+
+    #[test]
+    fn test_values_keep_their_storage_class() {
+        let db = open_fixture("mixed_types.db");
+
+        // NULL first, then numbers by value, text and blobs, like sqlite
+        let ids = |sql: &str| {
+            query(&db, sql)
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect_vec()
+        };
+        assert_eq!(
+            ids("SELECT id FROM mixed ORDER BY reading"),
+            vec!["3", "5", "7", "1", "6", "2", "8", "4"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM mixed ORDER BY reading DESC"),
+            vec!["4", "8", "2", "6", "1", "7", "5", "3"]
+        );
+
+        let result = db
+            .execute_query(parse_sql("SELECT MIN(reading), MAX(reading) FROM mixed").unwrap())
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Float(2.5), Value::Blob(vec![0x00, 0xff])]]
+        );
+        let result = db
+            .execute_query(parse_sql("SELECT reading FROM mixed WHERE id < 3").unwrap())
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Int(10)], vec![Value::Text("9".to_owned())]]
+        );
+    }
+
+    #[test]
+    fn test_typed_results() {
+        let db = open_fixture("companies.db");
+        let result = db
+            .execute_statement(parse_statement("SELECT COUNT(*), MAX(id) FROM companies").unwrap())
+            .unwrap();
+        assert_eq!(
+            result,
+            QueryResult {
+                columns: vec!["COUNT(*)".to_owned(), "MAX(id)".to_owned()],
+                rows: vec![vec![Value::Int(1000), Value::Int(1000)]],
+            }
+        );
+
+        let result = db
+            .execute_statement(parse_statement("PRAGMA table_info(companies)").unwrap())
+            .unwrap();
+        assert_eq!(result.columns[4], "dflt_value");
+        assert_eq!(
+            result.rows[0],
+            vec![
+                Value::Int(0),
+                Value::Text("id".to_owned()),
+                Value::Text("INTEGER".to_owned()),
+                Value::Int(0),
+                Value::Null,
+                Value::Int(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pragma_table_info() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            pragma(&db, "PRAGMA table_info(companies)"),
+            vec![
+                vec!["0", "id", "INTEGER", "0", "", "1"],
+                vec!["1", "name", "TEXT", "0", "", "0"],
+                vec!["2", "country", "TEXT", "0", "", "0"],
+                vec!["3", "industry", "TEXT", "0", "", "0"],
+                vec!["4", "year_founded", "INTEGER", "0", "", "0"],
+                vec!["5", "employees", "INTEGER", "0", "", "0"],
+            ]
+        );
+        assert!(pragma(&db, "PRAGMA table_info(missing)").is_empty());
+    }
+
+    #[test]
+    fn test_pragma_index_list_and_info() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            pragma(&db, "PRAGMA index_list(companies)"),
+            vec![vec!["0", "idx_companies_country", "0", "c", "0"]]
+        );
+        assert_eq!(
+            pragma(&db, "PRAGMA index_info(idx_companies_country)"),
+            vec![vec!["0", "2", "country"]]
+        );
+        assert!(pragma(&db, "PRAGMA index_info(missing)").is_empty());
+    }
+
+    #[test]
+    fn test_shared_lock_blocks_writers() {
+        let db = open_fixture("companies.db");
+        let writer = File::open(fixture_path("companies.db")).unwrap();
+
+        db.lock_shared().unwrap();
+        assert!(writer.try_lock().is_err());
+        // Other readers can still share the lock
+        assert!(writer.try_lock_shared().is_ok());
+        writer.unlock().unwrap();
+
+        db.unlock().unwrap();
+        assert!(writer.try_lock().is_ok());
+    }
+
+    #[test]
+    fn test_integer_serial_types() {
+        let db = open_fixture("integer_sizes.db");
+
+        let values: Vec<i64> = db
+            .execute_query(parse_sql("SELECT n FROM numbers").unwrap())
+            .unwrap()
+            .rows
+            .into_iter()
+            .map(|row| match row[..] {
+                [Value::Int(n)] => n,
+                _ => panic!("not an integer: {:?}", row),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                0,
+                1,
+                -1,
+                127,
+                -129,
+                40000,
+                -8388608,
+                2147483647,
+                -(1 << 31) - 1,
+                (1 << 47) - 1,
+                -(1 << 47),
+                i64::MAX,
+                i64::MIN
+            ]
+        );
+
+        // The view and the trigger have no root page
+        let root_pages: Vec<(&str, i64)> = db
+            .schemas
+            .iter()
+            .map(|schema| (schema.kind.as_str(), schema.root_page))
+            .collect();
+        assert_eq!(root_pages, vec![("table", 2), ("view", 0), ("trigger", 0)]);
+    }
+
+    #[test]
+    fn test_real_column() {
+        let db = open_fixture("readings.db");
+
+        // 4.0 is stored as the integer 4 but is still a REAL
+        let result = db
+            .execute_query(parse_sql("SELECT value FROM readings WHERE id > 2").unwrap())
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Float(4.0)],
+                vec![Value::Float(2.25)],
+                vec![Value::Null]
+            ]
+        );
+        assert_eq!(
+            query(&db, "SELECT id, value FROM readings WHERE value > 1"),
+            vec![vec!["1", "1.5"], vec!["3", "4.0"], vec!["4", "2.25"]]
+        );
+    }
+
+    #[test]
+    fn test_pragma_user_version_and_application_id() {
+        let db = open_fixture("readings.db");
+        assert_eq!(pragma(&db, "PRAGMA user_version"), vec![vec!["42"]]);
+        assert_eq!(pragma(&db, "PRAGMA application_id"), vec![vec!["1234567"]]);
+    }
+
+    #[test]
+    fn test_pragma_page_size_and_count() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(pragma(&db, "PRAGMA page_size"), vec![vec!["4096"]]);
+        assert_eq!(pragma(&db, "PRAGMA page_count"), vec![vec!["18"]]);
+    }
+
+    #[test]
+    fn test_where_references_base_column() {
+        let db = open_fixture("companies.db");
+
+        // `country` is both an alias and a column, WHERE uses the column
+        assert_eq!(
+            query(
+                &db,
+                "SELECT name AS country FROM companies WHERE country = 'usa' LIMIT 2"
+            ),
+            vec![vec!["company 8"], vec!["company 13"]]
+        );
+    }
+
+    #[test]
+    fn test_order_by_alias() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT name AS n FROM companies WHERE name LIKE 'company 1_' ORDER BY n DESC LIMIT 3"
+            ),
+            vec![vec!["company 19"], vec!["company 18"], vec!["company 17"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country AS c, COUNT(*) AS n FROM companies GROUP BY c ORDER BY n DESC, c LIMIT 3"
+            ),
+            vec![vec!["usa", "192"], vec!["brazil", "116"], vec!["france", "116"]]
+        );
+    }
+
+    #[test]
+    fn test_group_by_ordered_by_count() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country, COUNT(*) FROM companies GROUP BY country ORDER BY COUNT(*) DESC, country LIMIT 4"
+            ),
+            vec![
+                vec!["usa", "192"],
+                vec!["brazil", "116"],
+                vec!["france", "116"],
+                vec!["uk", "116"],
+            ]
+        );
+        // The aggregate doesn't have to be selected to order by it
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country FROM companies GROUP BY country ORDER BY COUNT(*) DESC, country DESC LIMIT 3"
+            ),
+            vec![vec!["usa"], vec!["uk"], vec!["france"]]
+        );
+    }
+
+    #[test]
+    fn test_order_by_aggregate_without_grouping_errors() {
+        let db = open_fixture("companies.db");
+        let sql = parse_sql("SELECT name FROM companies ORDER BY COUNT(*)").unwrap();
+
+        assert!(db.execute_query(sql).is_err());
+    }
+
+    #[test]
+    fn test_limit_on_group_by() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country, COUNT(*) FROM companies GROUP BY country LIMIT 2"
+            ),
+            vec![vec!["brazil", "116"], vec!["canada", "115"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country, COUNT(*) FROM companies GROUP BY country"
+            )
+            .len(),
+            8
+        );
+    }
+
+    #[test]
+    fn test_join_with_pushed_down_conditions() {
+        let db = open_fixture("library.db");
+
+        // Both conditions only involve one table, so they are applied while scanning it
+        assert_eq!(
+            query(
+                &db,
+                "SELECT authors.name, books.title FROM authors JOIN books ON authors.id = books.author_id WHERE books.year > 1900 AND authors.country = 'uk'"
+            ),
+            vec![
+                vec!["George Orwell", "Animal Farm"],
+                vec!["George Orwell", "Nineteen Eighty-Four"],
+            ]
+        );
+        // A condition on the right table, with the ON columns swapped
+        assert_eq!(
+            query(
+                &db,
+                "SELECT title, name FROM books INNER JOIN authors ON authors.id = books.author_id WHERE authors.name = 'Toni Morrison'"
+            ),
+            vec![vec!["Beloved", "Toni Morrison"], vec!["Jazz", "Toni Morrison"]]
+        );
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let db = open_fixture("library.db");
+
+        // author_id is indexed, the integer keys are compared as numbers
+        let expected = vec![vec!["6", "Beloved"], vec!["10", "Jazz"]];
+        assert_eq!(
+            query(&db, "SELECT id, title FROM books WHERE author_id = 4"),
+            expected
+        );
+        assert_eq!(
+            query(&db, "SELECT id, title FROM books WHERE author_id = '4'"),
+            expected
+        );
+        assert_eq!(
+            query(&db, "SELECT title FROM books WHERE id = 3.0"),
+            vec![vec!["The Sun Also Rises"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT title FROM books WHERE year = 1813.0"),
+            vec![vec!["Pride and Prejudice"]]
+        );
+        assert!(query(&db, "SELECT title FROM books WHERE title = 3").is_empty());
+    }
+
+    #[test]
+    fn test_join_with_conditions_on_both_tables() {
+        let db = open_fixture("library.db");
+
+        // The OR involves both tables, so it is checked on the joined records
+        assert_eq!(
+            query(
+                &db,
+                "SELECT books.title FROM books JOIN authors ON author_id = authors.id WHERE year < 1850 OR country = 'us' ORDER BY books.title"
+            ),
+            vec![
+                vec!["Beloved"],
+                vec!["Emma"],
+                vec!["Jazz"],
+                vec!["Persuasion"],
+                vec!["Pride and Prejudice"],
+                vec!["The Old Man and the Sea"],
+                vec!["The Sun Also Rises"],
+            ]
+        );
+        // Authors without books and books without an author aren't joined
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM authors JOIN books ON authors.id = books.author_id"
+            ),
+            vec![vec!["9"]]
+        );
+        // `id` is in both tables, so it has to be qualified
+        let sql =
+            parse_sql("SELECT id FROM authors JOIN books ON authors.id = books.author_id").unwrap();
+        assert!(db.execute_query(sql).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_join_shapes() {
+        let db = open_fixture("library.db");
+        let error = |sql: &str| {
+            db.execute_query(parse_sql(sql).unwrap())
+                .unwrap_err()
+                .to_string()
+        };
+
+        // The ON condition has to compare a column of each table
+        assert_eq!(
+            error("SELECT title FROM authors JOIN books ON books.id = books.author_id"),
+            "unsupported join condition: books.id = books.author_id"
+        );
+        assert_eq!(
+            error("SELECT title FROM authors JOIN books ON authors.id = books.missing"),
+            "unsupported join condition: authors.id = books.missing"
+        );
+        assert_eq!(
+            error("SELECT name FROM authors JOIN Authors ON authors.id = authors.id"),
+            "joining a table with itself is not supported"
+        );
+
+        // Only a single inner join on an equality is parsed
+        for sql in [
+            "SELECT title FROM authors LEFT JOIN books ON authors.id = books.author_id",
+            "SELECT title FROM authors CROSS JOIN books",
+            "SELECT title FROM authors JOIN books ON authors.id > books.author_id",
+            "SELECT title FROM authors JOIN books ON authors.id = books.author_id JOIN authors ON authors.id = books.id",
+        ] {
+            assert!(parse_sql(sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_order_by_column_not_selected() {
+        let db = open_fixture("library.db");
+
+        assert_eq!(
+            query(&db, "SELECT title FROM books ORDER BY year DESC LIMIT 3"),
+            vec![
+                vec!["Jazz"],
+                vec!["Beloved"],
+                vec!["The Old Man and the Sea"]
+            ]
+        );
+        assert_eq!(
+            query(&db, "SELECT title FROM books ORDER BY year ASC LIMIT 2"),
+            vec![vec!["Pride and Prejudice"], vec!["Emma"]]
+        );
+    }
+
+    #[test]
+    fn test_order_by_sorts_null_first() {
+        let db = open_fixture("readings.db");
+        let ids = |sql: &str| -> Vec<String> { query(&db, sql).concat() };
+
+        assert_eq!(
+            ids("SELECT id FROM readings ORDER BY value"),
+            vec!["2", "5", "1", "4", "3"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM readings ORDER BY value DESC"),
+            vec!["3", "4", "1", "2", "5"]
+        );
+    }
+
+    #[test]
+    fn test_offset_without_limit() {
+        let db = open_fixture("companies.db");
+
+        assert_eq!(
+            query(&db, "SELECT id FROM companies OFFSET 998"),
+            vec![vec!["999"], vec!["1000"]]
+        );
+        // After sorting, on the rows matching the index lookup
+        let ids = query(
+            &db,
+            "SELECT id FROM companies WHERE country = 'uk' ORDER BY id DESC OFFSET 113",
+        );
+        assert_eq!(ids, vec![vec!["22"], vec!["14"], vec!["6"]]);
+        // Skipping the only row of an aggregate leaves nothing
+        assert!(query(&db, "SELECT COUNT(*) FROM companies OFFSET 1").is_empty());
+    }
+
+    #[test]
+    fn test_order_by_keeps_scan_order_of_equal_keys() {
+        let db = open_fixture("companies.db");
+
+        for direction in ["ASC", "DESC"] {
+            let rows = query(
+                &db,
+                &format!(
+                    "SELECT country, id FROM companies ORDER BY country {}",
+                    direction
+                ),
+            );
+            assert_eq!(rows.len(), 1000);
+            for pair in rows.windows(2) {
+                let [(country, id), (next_country, next_id)] =
+                    [&pair[0], &pair[1]].map(|row| (&row[0], row[1].parse::<usize>().unwrap()));
+                if country == next_country {
+                    assert!(id < next_id, "{} before {} in {}", id, next_id, country);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_select_all_columns() {
+        let db = open_fixture("pk_second.db");
+
+        // In declared order, with the INTEGER PRIMARY KEY read from the rowid
+        let sql = "SELECT * FROM people WHERE age > 50";
+        assert_eq!(columns(&db, sql), vec!["name", "id", "age"]);
+        assert_eq!(
+            query(&db, sql),
+            vec![vec!["grace", "20", "85"], vec!["linus", "30", "54"]]
+        );
+
+        let db = open_fixture("library.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT * FROM authors JOIN books ON authors.id = books.author_id WHERE title = 'Jazz'"
+            ),
+            vec![vec!["4", "Toni Morrison", "us", "10", "Jazz", "4", "1992"]]
+        );
+    }
+
+    #[test]
+    fn test_descending_index_lookup() {
+        let db = open_fixture("desc_index.db");
+        // The index root, so lookups have to pick the right subtrees
+        let root = db.read_page_header(3).unwrap();
+        assert_eq!(root.page_type, BTreePage::InteriorIndex);
+
+        for (number, id) in [("000", "200"), ("007", "1"), ("150", "50"), ("199", "57")] {
+            let sql = format!(
+                "SELECT id FROM events WHERE name = 'event {} xxxxxxxxxxxxxxxxxxxx'",
+                number
+            );
+            assert_eq!(query(&db, &sql), vec![vec![id]], "event {}", number);
+        }
+        assert!(query(&db, "SELECT id FROM events WHERE name = 'event 200'").is_empty());
+    }
+
+    #[test]
+    fn test_between_index_range() {
+        let db = open_fixture("desc_index.db");
+        let reads = db.page_reads();
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM events WHERE name BETWEEN 'event 010' AND 'event 013z'"
+            ),
+            vec![vec!["30"], vec!["59"], vec!["116"], vec!["173"]]
+        );
+        // Only the part of the index with the range is read, not the whole table
+        let range_reads = db.page_reads() - reads;
+        query(&db, "SELECT id FROM events WHERE name LIKE 'event 01%'");
+        assert!(range_reads < db.page_reads() - reads - range_reads);
+
+        let db = open_fixture("library.db");
+        assert_eq!(
+            query(&db, "SELECT id FROM books WHERE author_id BETWEEN 2 AND 3"),
+            vec![vec!["3"], vec!["4"], vec!["5"], vec!["7"]]
+        );
+        assert!(query(&db, "SELECT id FROM books WHERE author_id BETWEEN 3 AND 2").is_empty());
+
+        let db = open_fixture("companies.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM companies WHERE country BETWEEN 'canada' AND 'india'"
+            ),
+            vec![vec!["461"]]
+        );
+    }
+
+    #[test]
+    fn test_covered_query_reads_only_the_index() {
+        let db = open_fixture("desc_index.db");
+        let reads = db.page_reads();
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id, name FROM events WHERE name = 'event 007 xxxxxxxxxxxxxxxxxxxx'"
+            ),
+            vec![vec!["1", "event 007 xxxxxxxxxxxxxxxxxxxx"]]
+        );
+        // The interior root page of the index and one of its leaves, no table pages
+        assert_eq!(db.page_reads() - reads, 2);
+
+        let db = open_fixture("companies.db");
+        let reads = db.page_reads();
+        let ids = query(&db, "SELECT id FROM companies WHERE country = 'india'");
+        let index_reads = db.page_reads() - reads;
+        // Selecting a column that isn't indexed reads the table as well
+        let names = query(
+            &db,
+            "SELECT id, name FROM companies WHERE country = 'india'",
+        );
+        assert!(db.page_reads() - reads - index_reads > index_reads);
+        assert_eq!(ids.len(), 115);
+        assert!(ids.iter().zip(&names).all(|(id, name)| id[0] == name[0]));
+        assert_eq!(
+            query(
+                &db,
+                "SELECT country, COUNT(*) FROM companies WHERE country IN ('india', 'canada') GROUP BY country"
+            ),
+            vec![vec!["canada", "115"], vec!["india", "115"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT name FROM companies WHERE country = 'india' AND name LIKE '%' LIMIT 1"
+            )
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_composite_index_leading_column() {
+        let db = open_fixture("composite_index.db");
+        assert_eq!(
+            pragma(&db, "PRAGMA index_info(idx_orders_customer_status)"),
+            vec![vec!["0", "1", "customer"], vec!["1", "2", "status"]]
+        );
+
+        let scan_reads = db.page_reads();
+        let scanned = query(
+            &db,
+            "SELECT id, status FROM orders WHERE customer LIKE 'customer 03'",
+        );
+        let scan_reads = db.page_reads() - scan_reads;
+
+        // Only the first column is constrained, the index is still used for it
+        let reads = db.page_reads();
+        let rows = query(
+            &db,
+            "SELECT id, status FROM orders WHERE customer = 'customer 03'",
+        );
+        assert_eq!(rows.len(), 30);
+        assert_eq!(rows, scanned);
+        // All the columns it needs are in the index, so the table isn't read
+        assert!(db.page_reads() - reads < scan_reads / 4);
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT amount FROM orders WHERE customer = 'customer 03' AND status = 'paid' LIMIT 2"
+            ),
+            vec![vec!["137"], vec!["417"]]
+        );
+    }
+
+    #[test]
+    fn test_composite_index_equality_prefix() {
+        let db = open_fixture("composite_index.db");
+        let ids = |rows: Vec<Vec<String>>| -> Vec<String> { rows.concat() };
+
+        // Both columns are looked up in the index, status is stored in descending order
+        assert_eq!(
+            ids(query(
+                &db,
+                "SELECT id FROM orders WHERE status = 'paid' AND customer = 'customer 03'"
+            )),
+            vec!["49", "109", "169", "229", "289", "349", "409", "469", "529", "589"]
+        );
+        // Only the rows with both values are fetched from the table
+        let reads = db.page_reads();
+        let amounts = query(
+            &db,
+            "SELECT amount FROM orders WHERE customer = 'customer 03' AND status = 'paid'",
+        );
+        let prefix_reads = db.page_reads() - reads;
+        let reads = db.page_reads();
+        let leading_amounts = query(
+            &db,
+            "SELECT amount FROM orders WHERE customer = 'customer 03' AND status LIKE 'paid'",
+        );
+        assert_eq!(amounts, leading_amounts);
+        assert!(prefix_reads < db.page_reads() - reads);
+
+        assert_eq!(
+            ids(query(
+                &db,
+                "SELECT id FROM orders WHERE customer IN ('customer 03', 'customer 04') AND status = 'new' LIMIT 4"
+            )),
+            vec!["9", "12", "69", "72"]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*) FROM orders WHERE customer BETWEEN 'customer 03' AND 'customer 05' AND status = 'shipped'"
+            ),
+            vec![vec!["30"]]
+        );
+    }
+
+    #[test]
+    fn test_index_range_for_comparisons() {
+        let db = open_fixture("desc_index.db");
+        let reads = db.page_reads();
+        assert_eq!(
+            query(&db, "SELECT id FROM events WHERE name > 'event 195'"),
+            vec![vec!["28"], vec!["57"], vec!["85"], vec!["114"], vec!["171"]]
+        );
+        // Only the end of the index is read
+        let range_reads = db.page_reads() - reads;
+        query(&db, "SELECT id FROM events WHERE name LIKE 'event 19%'");
+        assert!(range_reads < db.page_reads() - reads - range_reads);
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM events WHERE name <= 'event 003 xxxxxxxxxxxxxxxxxxxx'"
+            ),
+            vec![vec!["29"], vec!["86"], vec!["143"], vec!["200"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM events WHERE name < 'event 100'"),
+            vec![vec!["100"]]
+        );
+
+        let db = open_fixture("companies.db");
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies WHERE country >= 'u'"),
+            vec![vec!["308"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT COUNT(*) FROM companies WHERE country < 'b'"),
+            vec![vec!["0"]]
+        );
+
+        // A NULL key is not less than anything
+        let db = open_fixture("library.db");
+        assert_eq!(
+            query(&db, "SELECT id FROM books WHERE author_id < 3"),
+            vec![vec!["1"], vec!["2"], vec!["3"], vec!["7"], vec!["9"]]
+        );
+    }
+
+    #[test]
+    fn test_is_null() {
+        let db = open_fixture("text_values.db");
+        // The empty text is not NULL
+        assert_eq!(
+            query(&db, "SELECT id FROM labels WHERE label IS NULL"),
+            vec![vec!["5"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM labels WHERE label is not null"),
+            vec![vec!["1"], vec!["2"], vec!["3"], vec!["4"]]
+        );
+
+        let db = open_fixture("library.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT title FROM books WHERE author_id IS NULL OR year < 1800"
+            ),
+            vec![vec!["Anonymous Pamphlet"]]
+        );
+        assert!(query(&db, "SELECT id FROM books WHERE id IS NULL").is_empty());
+    }
+
+    #[test]
+    fn test_count_column_skips_nulls() {
+        let db = open_fixture("readings.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT COUNT(*), COUNT(value), COUNT(calibration), COUNT(DISTINCT sensor) FROM readings"
+            ),
+            vec![vec!["5", "3", "0", "3"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT sensor, COUNT(value) FROM readings GROUP BY sensor"
+            ),
+            vec![vec!["east", "0"], vec!["north", "1"], vec!["south", "2"]]
+        );
+
+        // Empty text is a value, unlike NULL
+        let db = open_fixture("text_values.db");
+        assert_eq!(
+            query(&db, "SELECT COUNT(label) FROM labels"),
+            vec![vec!["4"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM labels WHERE label = ''"),
+            vec![vec!["4"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM labels WHERE label != 'plain'"),
+            vec![vec!["2"], vec!["3"], vec!["4"]]
+        );
+    }
+
+    #[test]
+    fn test_scan_of_three_level_table() {
+        let db = open_fixture("deep_table.db");
+        let root = db.read_page_header(2).unwrap();
+        let child = db
+            .read_page_header(root.right_most_pointer.unwrap() as usize)
+            .unwrap();
+        assert_eq!(root.page_type, BTreePage::InteriorTable);
+        assert_eq!(child.page_type, BTreePage::InteriorTable);
+
+        let scanned: Vec<Record> = db.scan_table(2, i64::MIN).try_collect().unwrap();
+        assert_eq!(scanned.len(), 300);
+        assert!(scanned
+            .iter()
+            .enumerate()
+            .all(|(i, record)| record.row_id == i as i64 + 1));
+
+        // Starting from a rowid gives the same records from there on
+        let from_middle: Vec<Record> = db.scan_table(2, 151).try_collect().unwrap();
+        assert_eq!(
+            from_middle
+                .iter()
+                .map(|record| (&record.row_id, &record.columns))
+                .collect::<Vec<_>>(),
+            scanned[150..]
+                .iter()
+                .map(|record| (&record.row_id, &record.columns))
+                .collect::<Vec<_>>()
+        );
+
+        // Pages are only read as the records are consumed: the first record needs the root,
+        // one interior page and one leaf
+        let reads = db.page_reads();
+        let first = db.scan_table(2, i64::MIN).next().unwrap().unwrap();
+        assert_eq!(first.row_id, 1);
+        assert_eq!(db.page_reads() - reads, 3);
+
+        // So a LIMIT stops the scan early, even with a filter. Each leaf holds two rows.
+        let reads = db.page_reads();
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id FROM pages WHERE content IS NOT NULL LIMIT 2"
+            ),
+            vec![vec!["1"], vec!["2"]]
+        );
+        assert_eq!(db.page_reads() - reads, 4);
+    }
+
+    #[test]
+    fn test_avg_min_max() {
+        let db = open_fixture("library.db");
+
+        assert_eq!(
+            query(
+                &db,
+                "SELECT AVG(year), MIN(year), MAX(year), MIN(title), MAX(title) FROM books"
+            ),
+            vec![vec![
+                "1914.6",
+                "1813",
+                "1992",
+                "Animal Farm",
+                "The Sun Also Rises"
+            ]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT author_id, avg(year), min(title) FROM books GROUP BY author_id"
+            ),
+            vec![
+                vec!["", "1950.0", "Anonymous Pamphlet"],
+                vec!["1", "1815.0", "Emma"],
+                vec!["2", "1939.0", "The Old Man and the Sea"],
+                vec!["3", "1947.0", "Animal Farm"],
+                vec!["4", "1989.5", "Beloved"],
+            ]
+        );
+        // NULL without any values, like SUM
+        assert_eq!(
+            query(
+                &db,
+                "SELECT AVG(year), MIN(year), MAX(title), SUM(year) FROM books WHERE id > 100"
+            ),
+            vec![vec!["", "", "", ""]]
+        );
+
+        // NULLs are skipped
+        let db = open_fixture("readings.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT AVG(value), MIN(value), MAX(sensor) FROM readings"
+            ),
+            vec![vec!["2.58333333333333", "1.5", "south"]]
+        );
+    }
+
+    #[test]
+    fn test_printf() {
+        let db = open_fixture("companies.db");
+
+        let sql = "SELECT printf('%d-%s', id, name) FROM companies LIMIT 2";
+        assert_eq!(columns(&db, sql), vec!["printf('%d-%s', id, name)"]);
+        assert_eq!(
+            query(&db, sql),
+            vec![vec!["1-company 1"], vec!["2-company 2"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT FORMAT('%5s|%-3d|', country, id) AS f FROM companies WHERE id = 6"
+            ),
+            vec![vec!["   uk|6  |"]]
+        );
+
+        let db = open_fixture("readings.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT printf('%s=%.1f', sensor, value), printf(NULL, id) FROM readings WHERE id < 3"
+            ),
+            vec![vec!["north=1.5", ""], vec!["north=0.0", ""]]
+        );
+    }
+
+    #[test]
+    fn test_date_functions() {
+        let db = open_fixture("library.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT datetime(1700000000, 'unixepoch'), date('2024-02-28', '+1 day'), DATE(name) FROM authors LIMIT 1"
+            ),
+            vec![vec!["2023-11-14 22:13:20", "2024-02-29", ""]]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let db = open_fixture("library.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id, year / 100, year % 100 * 1.0, abs(1900 - year), year - id * 2 FROM books WHERE id = 6"
+            ),
+            vec![vec!["6", "19", "87.0", "87", "1975"]]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT 9223372036854775807 + 1, 7 / 2, 7.0 / 2, 1 / 0 FROM authors LIMIT 1"
+            ),
+            vec![vec!["9.22337203685478e+18", "3", "3.5", ""]]
+        );
+        assert_eq!(
+            query(&db, "SELECT id FROM books ORDER BY year % 100 LIMIT 3"),
+            vec![vec!["1"], vec!["2"], vec!["9"]]
+        );
+    }
+
+    #[test]
+    fn test_json_extract() {
+        let db = open_fixture("json_docs.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT id, json_extract(data, '$.user.name'), json_extract(data, '$.user.langs[0]'), json_extract(data, '$.score') FROM docs"
+            ),
+            vec![
+                vec!["1", "ada", "en", "9.5"],
+                vec!["2", "linus", "fi", "7"],
+                vec!["3", "grace", "", ""],
+            ]
+        );
+        assert_eq!(
+            query(
+                &db,
+                "SELECT json_extract(data, '$.user.langs') FROM docs WHERE id = 1"
+            ),
+            vec![vec![r#"["en","fr"]"#]]
+        );
+    }
+
+    #[test]
+    fn test_names_with_digits_and_spaces() {
+        let db = open_fixture("names.db");
+        assert_eq!(
+            query(
+                &db,
+                "SELECT col1, _note, \"size range\" FROM stats2024 WHERE col1 > 15"
+            ),
+            vec![vec!["20", "", "m-l"], vec!["30", "third", "l-xl"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT `size range` FROM stats2024 WHERE id = 1"),
+            vec![vec!["s-m"]]
+        );
+        assert_eq!(
+            query(&db, "SELECT [size range] FROM [stats2024] WHERE [id] = 2"),
+            vec![vec!["m-l"]]
+        );
+    }
+
+    #[test]
+    fn test_names_ignore_case() {
+        let db = open_fixture("companies.db");
+
+        // Plain columns are named as declared, other result columns as written
+        let sql = "SELECT Name, COUNTRY, count(ID) FROM Companies WHERE Country = 'brazil' GROUP BY Name ORDER BY NAME LIMIT 2";
+        assert_eq!(columns(&db, sql), vec!["name", "country", "count(ID)"]);
+        assert_eq!(
+            query(&db, sql),
+            vec![
+                vec!["company 1", "brazil", "1"],
+                vec!["company 105", "brazil", "1"]
+            ]
+        );
+
+        // The index on country is still used
+        let reads = db.page_reads();
+        query(&db, "SELECT id FROM companies WHERE country = 'brazil'");
+        let index_reads = db.page_reads() - reads;
+        let reads = db.page_reads();
+        query(&db, "SELECT ID FROM COMPANIES WHERE Country = 'brazil'");
+        assert_eq!(db.page_reads() - reads, index_reads);
+
+        assert_eq!(
+            pragma(&db, "PRAGMA table_info(COMPANIES)")[1],
+            vec!["1", "name", "TEXT", "0", "", "0"]
+        );
+        assert!(db
+            .execute_query(parse_sql("SELECT Nam FROM companies").unwrap())
+            .is_err());
+
+        let db = open_fixture("library.db");
+        let sql = "SELECT AUTHORS.name, Books.Title FROM Authors JOIN books ON authors.ID = Books.author_id WHERE BOOKS.id = 6";
+        assert_eq!(columns(&db, sql), vec!["name", "title"]);
+        assert_eq!(query(&db, sql), vec![vec!["Toni Morrison", "Beloved"]]);
+    }
+
+    #[test]
+    fn test_join_has_distinct_column_names() {
+        let db = open_fixture("library.db");
+
+        // Both tables have an `id`
+        assert_eq!(
+            columns(
+                &db,
+                "SELECT * FROM authors JOIN books ON authors.id = books.author_id WHERE books.id = 6"
+            ),
+            vec!["authors.id", "name", "country", "books.id", "title", "author_id", "year"]
+        );
+        assert_eq!(
+            columns(
+                &db,
+                "SELECT authors.name, books.title FROM authors JOIN books ON authors.id = books.author_id WHERE books.id = 6"
+            ),
+            vec!["name", "title"]
+        );
     }
 }
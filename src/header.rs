@@ -1,5 +1,102 @@
 use anyhow::{bail, Result};
 use std::convert::TryInto;
+use std::fmt;
+
+/// Every database file starts with this string
+pub const MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+// The 100 byte header at the start of the database file
+// https://www.sqlite.org/fileformat.html#the_database_header
+#[derive(Debug)]
+pub struct DatabaseHeader {
+    pub magic: [u8; 16],
+    pub page_size: u16, // 1 stands for 65536, which doesn't fit in the two bytes
+    pub write_version: u8, // file format write version, 1 for legacy and 2 for WAL
+    pub read_version: u8, // file format read version, 1 for legacy and 2 for WAL
+    pub reserved_bytes: u8, // unused space at the end of each page, e.g. for encryption extensions
+    pub database_size: u32, // in pages, only valid if the file was last written by sqlite 3.7.0 or later
+    pub largest_root_page: u32, // non-zero in auto_vacuum and incremental_vacuum databases, which have pointer map pages
+    pub incremental_vacuum: bool,
+    pub schema_format: u32, // schema format number (1-4), decides which schema features may be used
+    pub user_version: u32, // set by applications with PRAGMA user_version, e.g. to version their schema
+    pub application_id: u32, // set by applications with PRAGMA application_id to identify their files
+    pub text_encoding: TextEncoding,
+}
+
+impl DatabaseHeader {
+    /// Parses the first 100 bytes of the database file. Errors when they don't start with the
+    /// magic string, i.e. the file isn't a database.
+    pub fn parse(stream: &[u8; 100]) -> Result<Self> {
+        if &stream[..16] != MAGIC {
+            bail!("File is not a database: the header doesn't start with \"SQLite format 3\"");
+        }
+
+        Ok(DatabaseHeader {
+            magic: stream[..16].try_into()?,
+            page_size: u16::from_be_bytes(stream[16..18].try_into()?),
+            write_version: stream[18],
+            read_version: stream[19],
+            reserved_bytes: stream[20],
+            database_size: u32::from_be_bytes(stream[28..32].try_into()?),
+            largest_root_page: u32::from_be_bytes(stream[52..56].try_into()?),
+            incremental_vacuum: u32::from_be_bytes(stream[64..68].try_into()?) != 0,
+            schema_format: u32::from_be_bytes(stream[44..48].try_into()?),
+            user_version: u32::from_be_bytes(stream[60..64].try_into()?),
+            application_id: u32::from_be_bytes(stream[68..72].try_into()?),
+            text_encoding: TextEncoding::parse(u32::from_be_bytes(stream[56..60].try_into()?))?,
+        })
+    }
+
+    /// Number of bytes of each page that hold data, those before the reserved space.
+    pub fn usable_size(&self) -> usize {
+        self.page_size as usize - self.reserved_bytes as usize
+    }
+
+    /// Whether the database has pointer map pages.
+    pub fn has_ptrmap(&self) -> bool {
+        self.largest_root_page != 0
+    }
+
+    /// Errors when the schema format number isn't one sqlite defines (1 to 4),
+    /// as newer formats may use schema features that would be misparsed.
+    pub fn check_schema_format(&self) -> Result<()> {
+        if !(1..=4).contains(&self.schema_format) {
+            bail!("Unsupported schema format: {}", self.schema_format);
+        }
+        Ok(())
+    }
+}
+
+/// The encoding of all text in the database
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TextEncoding {
+    Utf8 = 1,
+    Utf16le = 2,
+    Utf16be = 3,
+}
+
+impl TextEncoding {
+    fn parse(value: u32) -> Result<Self> {
+        Ok(match value {
+            1 => TextEncoding::Utf8,
+            2 => TextEncoding::Utf16le,
+            3 => TextEncoding::Utf16be,
+            x => bail!("Invalid text encoding: {}", x),
+        })
+    }
+}
+
+// As `.dbinfo` shows it, e.g. `1 (utf8)`
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TextEncoding::Utf8 => "utf8",
+            TextEncoding::Utf16le => "utf16le",
+            TextEncoding::Utf16be => "utf16be",
+        };
+        write!(f, "{} ({})", *self as u32, name)
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum BTreePage {
@@ -27,13 +124,28 @@ impl PageHeader {
     /// Parses a page header stream into a page header
     pub fn parse(stream: &[u8]) -> Result<Self> {
         // https://www.sqlite.org/fileformat.html#b_tree_pages
-        let page_type = match stream[0] {
+        let Some(&page_type) = stream.first() else {
+            bail!("Page header is empty");
+        };
+        let page_type = match page_type {
             2 => BTreePage::InteriorIndex,
             5 => BTreePage::InteriorTable,
             10 => BTreePage::LeafIndex,
             13 => BTreePage::LeafTable,
             x => bail!("Invalid page value encountered: {}", x),
         };
+        let size = match page_type {
+            BTreePage::InteriorIndex | BTreePage::InteriorTable => 12,
+            BTreePage::LeafIndex | BTreePage::LeafTable => 8,
+        };
+        if stream.len() < size {
+            bail!(
+                "Page header is truncated: {} of {} bytes",
+                stream.len(),
+                size
+            );
+        }
+
         let first_free_block_start = u16::from_be_bytes(stream[1..3].try_into()?);
         let number_of_cells = u16::from_be_bytes(stream[3..5].try_into()?);
         let start_of_content_area = u16::from_be_bytes(stream[5..7].try_into()?);
@@ -64,7 +176,84 @@ impl PageHeader {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_header(name: &str) -> [u8; 100] {
+        let path = format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let bytes = std::fs::read(path).unwrap();
+        bytes[..100].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_schema_format() {
+        let header = DatabaseHeader::parse(&fixture_header("companies.db")).unwrap();
+        assert_eq!(header.page_size, 4096);
+        assert_eq!(header.schema_format, 4);
+        assert!(header.check_schema_format().is_ok());
+
+        let mut bytes = fixture_header("companies.db");
+        bytes[44..48].copy_from_slice(&5u32.to_be_bytes());
+        let header = DatabaseHeader::parse(&bytes).unwrap();
+        assert!(header.check_schema_format().is_err());
+    }
+
+    #[test]
+    fn test_application_fields() {
+        let header = DatabaseHeader::parse(&fixture_header("readings.db")).unwrap();
+        assert_eq!(header.user_version, 42);
+        assert_eq!(header.application_id, 1234567);
+
+        let header = DatabaseHeader::parse(&fixture_header("companies.db")).unwrap();
+        assert_eq!(header.user_version, 0);
+        assert_eq!(header.application_id, 0);
+    }
+
+    #[test]
+    fn test_auto_vacuum_fields() {
+        let header = DatabaseHeader::parse(&fixture_header("auto_vacuum.db")).unwrap();
+        assert_eq!(header.largest_root_page, 4);
+        assert!(!header.incremental_vacuum);
+        assert!(header.has_ptrmap());
 
+        let header = DatabaseHeader::parse(&fixture_header("companies.db")).unwrap();
+        assert!(!header.has_ptrmap());
+    }
 
+    #[test]
+    fn test_file_format_fields() {
+        let header = DatabaseHeader::parse(&fixture_header("companies.db")).unwrap();
+        assert_eq!(&header.magic, MAGIC);
+        assert_eq!(header.write_version, 1);
+        assert_eq!(header.read_version, 1);
+        assert_eq!(header.reserved_bytes, 0);
+        assert_eq!(header.text_encoding, TextEncoding::Utf8);
+        assert_eq!(header.text_encoding.to_string(), "1 (utf8)");
 
+        let header = DatabaseHeader::parse(&fixture_header("overflow.db")).unwrap();
+        assert_eq!(header.page_size, 1024);
+        assert_eq!(header.database_size, 11);
+    }
 
+    #[test]
+    fn test_invalid_header_errors() {
+        let mut bytes = fixture_header("companies.db");
+        bytes[..16].copy_from_slice(b"SQLite format 4\0");
+        assert!(DatabaseHeader::parse(&bytes).is_err());
+        assert!(DatabaseHeader::parse(&[0; 100]).is_err());
+
+        let mut bytes = fixture_header("companies.db");
+        bytes[56..60].copy_from_slice(&7u32.to_be_bytes());
+        assert!(DatabaseHeader::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_truncated_page_header_errors() {
+        // Found by fuzzing: these used to index past the end of the input
+        assert!(PageHeader::parse(&[]).is_err());
+        assert!(PageHeader::parse(&[13, 0, 0]).is_err());
+        assert!(PageHeader::parse(&[5, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(PageHeader::parse(&[13, 0, 0, 0, 1, 0, 0, 0]).is_ok());
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+
+use crate::record::Value;
+use crate::util;
+
+/// A parsed JSON value. Numbers keep their text, so they are written back as they were.
+#[derive(Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// sqlite's `json_extract(json, path, ...)`. With a single path the value it selects: NULL,
+/// 1 or 0 for true and false, a number, the text of a string, or the minified JSON of an
+/// array or object. With several paths a JSON array of the selected values.
+///
+/// A path is `$` followed by `.key`, `."key"`, `[N]` or `[#-N]` steps. NULL if the path
+/// selects nothing, and also when an argument is NULL, the JSON is malformed or a path is
+/// invalid, which are errors in sqlite.
+pub fn json_extract(arguments: &[Option<String>]) -> Value {
+    let Some((Some(json), paths)) = arguments.split_first() else {
+        return Value::Null;
+    };
+    let Some(json) = parse(json) else {
+        return Value::Null;
+    };
+
+    let mut selected = vec![];
+    for path in paths {
+        match path.as_deref().and_then(|path| select(&json, path)) {
+            Some(value) => selected.push(value),
+            None if paths.len() == 1 => return Value::Null,
+            // Missing values are null in the array
+            None => selected.push(&Json::Null),
+        }
+    }
+    match selected.as_slice() {
+        [value] => value.to_value(),
+        _ => Value::Text(format!(
+            "[{}]",
+            selected
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        )),
+    }
+}
+
+impl Json {
+    fn to_value(&self) -> Value {
+        match self {
+            Json::Null => Value::Null,
+            Json::Bool(bool) => Value::Int(*bool as i64),
+            Json::Number(number) => number
+                .parse()
+                .map(Value::Int)
+                .unwrap_or_else(|_| Value::Float(number.parse().unwrap_or(0.0))),
+            Json::String(string) => Value::Text(string.clone()),
+            Json::Array(_) | Json::Object(_) => Value::Text(self.to_string()),
+        }
+    }
+}
+
+// Minified JSON
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(bool) => write!(f, "{}", bool),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(string) => write!(f, "{}", util::json_string(string)),
+            Json::Array(elements) => {
+                let elements = elements.iter().map(ToString::to_string);
+                write!(f, "[{}]", elements.collect::<Vec<_>>().join(","))
+            }
+            Json::Object(members) => {
+                let members = members
+                    .iter()
+                    .map(|(key, value)| format!("{}:{}", util::json_string(key), value));
+                write!(f, "{{{}}}", members.collect::<Vec<_>>().join(","))
+            }
+        }
+    }
+}
+
+/// The value a path selects. None if it selects nothing or isn't a valid path.
+fn select<'a>(json: &'a Json, path: &str) -> Option<&'a Json> {
+    let mut path = path.strip_prefix('$')?;
+    let mut json = json;
+    while !path.is_empty() {
+        if let Some(rest) = path.strip_prefix('.') {
+            // A key runs up to the next step, unless it is quoted
+            let (key, rest) = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"')?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                None => rest.split_at(rest.find(['.', '[']).unwrap_or(rest.len())),
+            };
+            // The first of duplicate keys counts
+            json = match json {
+                Json::Object(members) => &members.iter().find(|(name, _)| name == key)?.1,
+                _ => return None,
+            };
+            path = rest;
+        } else if let Some(rest) = path.strip_prefix('[') {
+            let (index, rest) = rest.split_once(']')?;
+            let Json::Array(elements) = json else {
+                return None;
+            };
+            // `#-N` counts back from the end of the array
+            let index = match index.strip_prefix("#-") {
+                Some(from_end) => elements.len().checked_sub(from_end.parse().ok()?)?,
+                None => index.parse().ok()?,
+            };
+            json = elements.get(index)?;
+            path = rest;
+        } else {
+            return None;
+        }
+    }
+    Some(json)
+}
+
+/// How deep arrays and objects can nest, the same limit as sqlite's. Deeper JSON is taken
+/// as malformed rather than parsed until the stack runs out.
+const MAX_DEPTH: usize = 1000;
+
+/// Parse a JSON text. None if it is malformed.
+pub fn parse(text: &str) -> Option<Json> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        position: 0,
+    };
+    let json = parser.value(0)?;
+    parser.skip_whitespace();
+    (parser.position == parser.bytes.len()).then_some(json)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    // Consume `expected` after any whitespace
+    fn eat(&mut self, expected: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(expected);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn literal(&mut self, literal: &str, json: Json) -> Option<Json> {
+        let end = self.position + literal.len();
+        (self.bytes.get(self.position..end)? == literal.as_bytes()).then(|| {
+            self.position = end;
+            json
+        })
+    }
+
+    // `depth` is the number of arrays and objects the value is in
+    fn value(&mut self, depth: usize) -> Option<Json> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'[' | b'{' if depth == MAX_DEPTH => None,
+            b'n' => self.literal("null", Json::Null),
+            b't' => self.literal("true", Json::Bool(true)),
+            b'f' => self.literal("false", Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' => {
+                self.position += 1;
+                let mut elements = vec![];
+                if !self.eat(b']') {
+                    loop {
+                        elements.push(self.value(depth + 1)?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Array(elements))
+            }
+            b'{' => {
+                self.position += 1;
+                let mut members = vec![];
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        if !self.eat(b':') {
+                            return None;
+                        }
+                        members.push((key, self.value(depth + 1)?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Object(members))
+            }
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => None,
+        }
+    }
+
+    // `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`
+    fn number(&mut self) -> Option<Json> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let start = parser.position;
+            while parser.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            parser.position > start
+        };
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        let integer_start = self.position;
+        if !digits(self) || (self.bytes[integer_start] == b'0' && self.position > integer_start + 1)
+        {
+            return None;
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if !digits(self) {
+                return None;
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                return None;
+            }
+        }
+        let number = std::str::from_utf8(&self.bytes[start..self.position]).ok()?;
+        Some(Json::Number(number.to_owned()))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.peek() != Some(b'"') {
+            return None;
+        }
+        self.position += 1;
+        let mut bytes = vec![];
+        loop {
+            let byte = self.peek()?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.position += 1;
+                    let ch = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    };
+                    bytes.extend(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1f => return None,
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).ok()
+    }
+
+    // The character of a `\uXXXX` escape, the `\u` already consumed. Characters outside the
+    // basic multilingual plane are written as a surrogate pair of two escapes.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let first = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&first) {
+            return char::from_u32(first);
+        }
+        if self.bytes.get(self.position..self.position + 2)? != b"\\u" {
+            return None;
+        }
+        self.position += 2;
+        let second = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&second) {
+            return None;
+        }
+        char::from_u32(0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = std::str::from_utf8(self.bytes.get(self.position..self.position + 4)?).ok()?;
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        self.position += 4;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(json: &str, paths: &[&str]) -> Value {
+        let mut arguments = vec![Some(json.to_owned())];
+        arguments.extend(paths.iter().map(|path| Some(path.to_string())));
+        json_extract(&arguments)
+    }
+
+    #[test]
+    fn test_extract_object_keys() {
+        let json = r#"{"a": {"b": {"c": "deep"}, "n": 2.50}, "a b": true, "a": 7}"#;
+        assert_eq!(extract(json, &["$.a.b.c"]), Value::Text("deep".to_owned()));
+        assert_eq!(extract(json, &["$.a.n"]), Value::Float(2.5));
+        assert_eq!(extract(json, &[r#"$."a b""#]), Value::Int(1));
+        // Objects and arrays come back as minified JSON, numbers as written
+        assert_eq!(
+            extract(json, &["$.a"]),
+            Value::Text(r#"{"b":{"c":"deep"},"n":2.50}"#.to_owned())
+        );
+        assert_eq!(extract(json, &["$.missing"]), Value::Null);
+        assert_eq!(extract(json, &["$.a.b.c.d"]), Value::Null);
+    }
+
+    #[test]
+    fn test_extract_array_elements() {
+        let json = r#"{"tags": ["x", 1e2, null, [-3]], "escaped": "qé\n😀"}"#;
+        assert_eq!(extract(json, &["$.tags[0]"]), Value::Text("x".to_owned()));
+        assert_eq!(extract(json, &["$.tags[1]"]), Value::Float(100.0));
+        assert_eq!(extract(json, &["$.tags[2]"]), Value::Null);
+        assert_eq!(extract(json, &["$.tags[3][0]"]), Value::Int(-3));
+        assert_eq!(
+            extract(json, &["$.tags[#-1]"]),
+            Value::Text("[-3]".to_owned())
+        );
+        assert_eq!(extract(json, &["$.tags[4]"]), Value::Null);
+        assert_eq!(
+            extract(json, &["$.escaped"]),
+            Value::Text("qé\n😀".to_owned())
+        );
+        assert_eq!(
+            extract(json, &["$.tags[0]", "$.none", "$.tags[3]"]),
+            Value::Text(r#"["x",null,[-3]]"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        for json in ["{bad", r#"{"a": 1,}"#, "01", "[1] 2", r#""tab	""#, ""] {
+            assert_eq!(parse(json), None, "{}", json);
+        }
+        assert_eq!(extract(r#"{"a":1}"#, &["a"]), Value::Null);
+        assert_eq!(extract(" 5 ", &["$"]), Value::Int(5));
+        assert_eq!(json_extract(&[None, Some("$".to_owned())]), Value::Null);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)), None);
+        assert_eq!(extract(&"[".repeat(200_000), &["$"]), Value::Null);
+        let objects = format!(
+            "{}1{}",
+            r#"{"a":"#.repeat(MAX_DEPTH + 1),
+            "}".repeat(MAX_DEPTH + 1)
+        );
+        assert_eq!(parse(&objects), None);
+    }
+}
@@ -1,8 +1,17 @@
 pub mod header;
 pub mod varint;
 pub mod record;
+pub mod overflow;
+pub mod ptrmap;
 pub mod schema;
 pub mod db;
 pub mod select_sql;
 pub mod creation_sql;
-pub mod util;
\ No newline at end of file
+pub mod util;
+pub mod pattern;
+pub mod printf;
+pub mod datetime;
+pub mod arithmetic;
+pub mod json;
+#[cfg(feature = "columnar")]
+pub mod columnar;
\ No newline at end of file
@@ -1,59 +1,573 @@
 use anyhow::{bail, Result};
 use itertools::Itertools;
-use sqlite_starter_rust::db::{get_page_header, parse_schemas, DB};
+use sqlite_starter_rust::db::{pending_writes, QueryResult, DB};
+use sqlite_starter_rust::record::Value;
 use sqlite_starter_rust::select_sql;
 use sqlite_starter_rust::util;
-use std::convert::TryInto;
-use std::fs::File;
-use std::io::prelude::*;
+use std::io::Write;
+use std::path::Path;
 
-fn get_page_size(file: &mut File) -> Result<u16> {
-    //read first 100 bytes from file
-    let mut buffer = [0; 100];
-    file.read_exact(&mut buffer)?;
-    //get page size
-    let page_size = u16::from_be_bytes(TryInto::<[u8; 2]>::try_into(&buffer[16..18]).unwrap());
-    Ok(page_size)
+/// How result rows are printed
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputMode {
+    List, // columns joined by the separator
+    Tabs, // columns joined by tabs, with tabs, newlines and backslashes in values escaped
+    Json, // an array with an object per row, keyed by column name
+}
+
+/// Settings for printing the results of statements, changed by the dot commands.
+struct Output {
+    separator: String, // joins the columns of printed rows
+    headers: bool,     // print the column names before the rows in list mode
+    row_counts: bool,  // print the number of rows of each statement to stderr
+    mode: OutputMode,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self {
+            separator: "|".to_owned(),
+            headers: false,
+            row_counts: false,
+            mode: OutputMode::List,
+        }
+    }
+}
+
+impl Output {
+    /// Write the rows of a result to `out`.
+    /// With `row_counts` a summary line with the number of rows follows on `err`, so it
+    /// stays out of data piped from `out`.
+    fn write(
+        &self,
+        result: &QueryResult,
+        out: &mut impl Write,
+        err: &mut impl Write,
+    ) -> Result<()> {
+        for line in self.render(result) {
+            writeln!(out, "{}", line)?;
+        }
+
+        if self.row_counts {
+            let plural = if result.rows.len() == 1 { "" } else { "s" };
+            writeln!(err, "({} row{})", result.rows.len(), plural)?;
+        }
+
+        Ok(())
+    }
+
+    /// Format the rows of a result as output lines in the current output mode.
+    fn render(&self, result: &QueryResult) -> Vec<String> {
+        let QueryResult { columns, rows } = result;
+        match self.mode {
+            OutputMode::List => {
+                let header =
+                    (self.headers && !rows.is_empty()).then(|| columns.join(&self.separator));
+                header
+                    .into_iter()
+                    .chain(
+                        rows.iter()
+                            .map(|row| row.iter().map(format_value).join(&self.separator)),
+                    )
+                    .collect()
+            }
+            OutputMode::Tabs => {
+                let header = (self.headers && !rows.is_empty())
+                    .then(|| columns.iter().map(|column| tsv_escape(column)).join("\t"));
+                header
+                    .into_iter()
+                    .chain(rows.iter().map(|row| {
+                        row.iter()
+                            .map(|value| tsv_escape(&format_value(value)))
+                            .join("\t")
+                    }))
+                    .collect()
+            }
+            // Like sqlite: `[{...},` on the first line, one object per line and `]` after the
+            // last. Nothing at all for an empty result.
+            OutputMode::Json => {
+                let last = rows.len().saturating_sub(1);
+                rows.iter()
+                    .enumerate()
+                    .map(|(i, row)| {
+                        let object = columns
+                            .iter()
+                            .zip(row)
+                            .map(|(column, value)| {
+                                format!("{}:{}", util::json_string(column), json_value(value))
+                            })
+                            .join(",");
+                        let open = if i == 0 { "[" } else { "" };
+                        let close = if i == last { "]" } else { "," };
+                        format!("{}{{{}}}{}", open, object, close)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A value as printed in the output. Blobs are written as hex literals like `X'deadbeef'`,
+/// as their bytes are rarely printable.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Blob(blob) => {
+            let hex: String = blob.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Escape a value for a TSV column, so that tabs and newlines in it can't be mistaken for
+/// column or row separators. NUL bytes, which text can hold too, are escaped as `\0`.
+fn tsv_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\0', "\\0")
+}
+
+/// A result value as JSON, numbers as numbers and text and blobs as strings.
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Int(_) | Value::Float(_) => value.to_string(),
+        value => util::json_string(&format_value(value)),
+    }
 }
 
 fn main() -> Result<()> {
-    // Parse arguments
-    let args = std::env::args().collect::<Vec<_>>();
+    // Parse arguments. Flags can appear anywhere and are removed from the positional arguments
+    let mut strict = false;
+    let mut lock = false;
+    let mut readonly_strict = false;
+    let mut row_counts = false;
+    let mut separator = None;
+    let mut args = vec![];
+    let mut raw_args = std::env::args();
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--strict" => strict = true,
+            "--lock" => lock = true,
+            "--readonly-strict" => readonly_strict = true,
+            "--row-counts" => row_counts = true,
+            "-separator" => match raw_args.next() {
+                Some(value) => separator = Some(value),
+                None => bail!("Missing value for -separator"),
+            },
+            _ => args.push(arg),
+        }
+    }
+
     match args.len() {
         0 | 1 => bail!("Missing <database path> and <command>"),
         2 => bail!("Missing <command>"),
         _ => {}
     }
 
-    // Read database file into database
-    let mut file = File::open(&args[1])?;
+    // Read database file into database. Opened read-only, this never writes the database.
+    let mut db = DB::open(&args[1])?;
+    if readonly_strict {
+        for warning in pending_writes(Path::new(&args[1])) {
+            eprintln!("warning: {}", warning);
+        }
+    }
 
-    let page_size = get_page_size(&mut file)?;
-    let first_page = util::read_page(&file, page_size, 1)?;
+    if let Err(err) = db.read_header()?.check_schema_format() {
+        if strict {
+            return Err(err);
+        }
+        eprintln!("warning: {}", err);
+    }
+    db.strict = strict;
+    if lock {
+        // Held until the process exits
+        db.lock_shared()?;
+    }
+    let mut output = Output {
+        row_counts,
+        ..Output::default()
+    };
+    if let Some(separator) = separator {
+        output.separator = separator;
+    }
 
-    // Parse command and act accordingly
-    let command = &args[2];
+    // Run every command in order, so settings like .separator apply to the commands after them
+    for command in &args[2..] {
+        run_command(&db, &mut output, command)?;
+    }
 
-    // On first page first 100 bytes are database header
-    let page_header = get_page_header(&first_page[100..])?;
-    let schemas = parse_schemas(&first_page, page_header.number_of_cells)?;
-    let db = DB::new(page_size, schemas, file);
+    Ok(())
+}
 
-    match command.as_str() {
+/// Parse command and act accordingly
+fn run_command(db: &DB, output: &mut Output, command: &str) -> Result<()> {
+    match command {
         ".dbinfo" => {
-            println!("database page size: {}", db.page_size);
-            println!("number of tables: {}", db.schemas.len());
+            // Labels padded to the same width as the sqlite3 shell does
+            let header = db.read_header()?;
+            let count = |kind: &str| db.schemas.iter().filter(|s| s.kind == kind).count();
+            let fields = [
+                ("database page size:", db.page_size.to_string()),
+                ("write format:", header.write_version.to_string()),
+                ("read format:", header.read_version.to_string()),
+                ("reserved bytes:", header.reserved_bytes.to_string()),
+                ("database page count:", header.database_size.to_string()),
+                ("schema format:", header.schema_format.to_string()),
+                ("autovacuum top root:", header.largest_root_page.to_string()),
+                (
+                    "incremental vacuum:",
+                    (header.incremental_vacuum as u8).to_string(),
+                ),
+                ("text encoding:", header.text_encoding.to_string()),
+                ("user version:", header.user_version.to_string()),
+                ("application id:", header.application_id.to_string()),
+                ("number of tables:", count("table").to_string()),
+                ("number of indexes:", count("index").to_string()),
+                ("number of triggers:", count("trigger").to_string()),
+                ("number of views:", count("view").to_string()),
+            ];
+            for (label, value) in fields {
+                println!("{:<20} {}", label, value);
+            }
         }
         ".tables" => {
-            let resp = db.schemas.iter().map(|schema| &schema.table_name).join(" ");
+            let resp = db.table_names().join(" ");
             println!("{}", resp);
         }
 
+        ".schema" => {
+            for sql in db.schema_sql(None) {
+                println!("{};", sql);
+            }
+        }
+
+        ".indexes" => {
+            // A line per table with the names of its indexes
+            for (table, indexes) in db.index_names(None) {
+                println!("{}: {}", table, indexes.join(" "));
+            }
+        }
+
+        command if command.starts_with(".indexes ") => {
+            let table = command[".indexes ".len()..].trim();
+            for (table, indexes) in db.index_names(Some(table)) {
+                println!("{}: {}", table, indexes.join(" "));
+            }
+        }
+
+        command if command.starts_with(".schema ") => {
+            // Only the table and the indexes and triggers on it
+            let table = command[".schema ".len()..].trim();
+            for sql in db.schema_sql(Some(table)) {
+                println!("{};", sql);
+            }
+        }
+
+        command if command.starts_with(".page ") => {
+            // Dump the raw bytes of a page followed by its decoded btree header
+            let page_number: usize = command[".page ".len()..].trim().parse()?;
+            let page_count = db.page_count()?;
+            if page_number == 0 || page_number as u64 > page_count {
+                bail!(
+                    "No page {}, the database has pages 1 to {}",
+                    page_number,
+                    page_count
+                );
+            }
+            let page = util::read_page(&db.file, db.page_size, page_number)?;
+            println!("{}", util::hex_dump(&page));
+
+            // Pointer map pages have no btree header, just an entry for each following page
+            if db.is_ptrmap_page(page_number)? {
+                println!("page type: pointer map");
+                for entry in db.read_ptrmap_page(page_number)? {
+                    println!(
+                        "page {}: {:?}, parent {}",
+                        entry.page_number, entry.page_type, entry.parent
+                    );
+                }
+                return Ok(());
+            }
+
+            let header = db.read_page_header(page_number)?;
+            println!("page type: {:?}", header.page_type);
+            println!("number of cells: {}", header.number_of_cells);
+            println!("start of content area: {}", header.start_of_content_area);
+            println!("first freeblock: {}", header.first_free_block_start);
+            println!("fragmented free bytes: {}", header.fragmented_free_bytes);
+            if let Some(pointer) = header.right_most_pointer {
+                println!("right most pointer: {}", pointer);
+            }
+        }
+
+        command if command.starts_with(".read ") => {
+            // Run every statement in the given file
+            let path = command[".read ".len()..].trim();
+            let input = std::fs::read_to_string(path)?;
+            for statement in select_sql::parse_statements(&input)? {
+                let result = db.execute_statement(statement)?;
+                output.write(&result, &mut std::io::stdout(), &mut std::io::stderr())?;
+            }
+        }
+
+        command if command.starts_with(".ast ") => {
+            // Print how the parser understood each statement
+            for statement in select_sql::parse_statements(&command[".ast ".len()..])? {
+                println!("{:#?}", statement);
+            }
+        }
+
+        command if command.starts_with(".separator ") => {
+            output.separator = command[".separator ".len()..].to_owned();
+        }
+
+        command if command.starts_with(".headers ") => {
+            output.headers = match command[".headers ".len()..].trim() {
+                "on" => true,
+                "off" => false,
+                value => bail!("Expected on or off for .headers, got {}", value),
+            };
+        }
+
+        command if command.starts_with(".rowcounts ") => {
+            output.row_counts = match command[".rowcounts ".len()..].trim() {
+                "on" => true,
+                "off" => false,
+                value => bail!("Expected on or off for .rowcounts, got {}", value),
+            };
+        }
+
+        command if command.starts_with(".mode ") => {
+            output.mode = match command[".mode ".len()..].trim() {
+                "list" => OutputMode::List,
+                "tabs" => OutputMode::Tabs,
+                "json" => OutputMode::Json,
+                mode => bail!("Unsupported output mode: {}", mode),
+            };
+        }
+
         query => {
-            let query = select_sql::parse_sql(query)?;
-            db.process_query(query)?;
+            for statement in select_sql::parse_statements(query)? {
+                let result = db.execute_statement(statement)?;
+                output.write(&result, &mut std::io::stdout(), &mut std::io::stderr())?;
+            }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlite_starter_rust::select_sql::parse_statement;
+
+    fn open_fixture(name: &str) -> DB {
+        DB::open(format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    }
+
+    fn lines(db: &DB, output: &Output, sql: &str) -> Vec<String> {
+        output.render(&db.execute_statement(parse_statement(sql).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_page_out_of_range() {
+        let db = open_fixture("companies.db");
+        let mut output = Output::default();
+        let page_count = db.page_count().unwrap();
+
+        for page_number in [0, page_count + 1, 99999] {
+            let err = run_command(&db, &mut output, &format!(".page {}", page_number)).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "No page {}, the database has pages 1 to {}",
+                    page_number, page_count
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_separator_changes_output_join() {
+        let db = open_fixture("companies.db");
+        let mut output = Output::default();
+        let sql = "SELECT id, name, country FROM companies LIMIT 1";
+        assert_eq!(lines(&db, &output, sql), vec!["1|company 1|brazil"]);
+
+        output.separator = ",".to_owned();
+        assert_eq!(lines(&db, &output, sql), vec!["1,company 1,brazil"]);
+    }
+
+    #[test]
+    fn test_row_count_summary_goes_to_stderr() {
+        let db = open_fixture("companies.db");
+        let mut output = Output::default();
+        let run = |output: &Output, sql: &str| {
+            let (mut out, mut err) = (vec![], vec![]);
+            let result = db.execute_statement(parse_statement(sql).unwrap()).unwrap();
+            output.write(&result, &mut out, &mut err).unwrap();
+            (
+                String::from_utf8(out).unwrap(),
+                String::from_utf8(err).unwrap(),
+            )
+        };
+
+        let sql = "SELECT id FROM companies LIMIT 3";
+        assert_eq!(run(&output, sql), ("1\n2\n3\n".to_owned(), String::new()));
+
+        output.row_counts = true;
+        assert_eq!(
+            run(&output, sql),
+            ("1\n2\n3\n".to_owned(), "(3 rows)\n".to_owned())
+        );
+        assert_eq!(
+            run(&output, "SELECT COUNT(*) FROM companies").1,
+            "(1 row)\n"
+        );
+        assert_eq!(
+            run(&output, "SELECT id FROM companies WHERE country = 'mars'"),
+            (String::new(), "(0 rows)\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_headers() {
+        let db = open_fixture("companies.db");
+        let output = Output {
+            headers: true,
+            ..Output::default()
+        };
+
+        assert_eq!(
+            lines(&db, &output, "SELECT COUNT(*) AS total FROM companies"),
+            vec!["total", "1000"]
+        );
+        assert!(lines(&db, &output, "SELECT id FROM companies WHERE id = 0").is_empty());
+    }
+
+    #[test]
+    fn test_json_mode() {
+        let db = open_fixture("companies.db");
+        let output = Output {
+            mode: OutputMode::Json,
+            ..Output::default()
+        };
+
+        assert_eq!(
+            lines(&db, &output, "SELECT COUNT(*) AS total FROM companies"),
+            vec![r#"[{"total":1000}]"#]
+        );
+        assert_eq!(
+            lines(
+                &db,
+                &output,
+                "SELECT id, name AS company FROM companies LIMIT 2"
+            ),
+            vec![
+                r#"[{"id":1,"company":"company 1"},"#,
+                r#"{"id":2,"company":"company 2"}]"#,
+            ]
+        );
+        assert!(lines(&db, &output, "SELECT id FROM companies WHERE id = 0").is_empty());
+
+        // Both tables of the join have an `id`
+        let db = open_fixture("library.db");
+        assert_eq!(
+            lines(
+                &db,
+                &output,
+                "SELECT * FROM authors JOIN books ON authors.id = books.author_id WHERE books.id = 6"
+            ),
+            vec![
+                r#"[{"authors.id":4,"name":"Toni Morrison","country":"us","books.id":6,"title":"Beloved","author_id":4,"year":1987}]"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_keeps_value_types() {
+        let db = open_fixture("mixed_types.db");
+        let output = Output {
+            mode: OutputMode::Json,
+            ..Output::default()
+        };
+
+        // Text that reads as a number is still a string
+        assert_eq!(
+            lines(
+                &db,
+                &output,
+                "SELECT id, reading FROM mixed WHERE id IN (1, 2, 3, 5, 6)"
+            ),
+            vec![
+                r#"[{"id":1,"reading":10},"#,
+                r#"{"id":2,"reading":"9"},"#,
+                r#"{"id":3,"reading":null},"#,
+                r#"{"id":5,"reading":2.5},"#,
+                r#"{"id":6,"reading":"10"}]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blobs_print_as_hex() {
+        let db = open_fixture("mixed_types.db");
+        let mut output = Output::default();
+        let sql = "SELECT id, reading FROM mixed WHERE id = 4";
+
+        assert_eq!(lines(&db, &output, sql), vec!["4|X'00ff'"]);
+        output.mode = OutputMode::Tabs;
+        assert_eq!(lines(&db, &output, sql), vec!["4\tX'00ff'"]);
+        output.mode = OutputMode::Json;
+        assert_eq!(
+            lines(&db, &output, sql),
+            vec![r#"[{"id":4,"reading":"X'00ff'"}]"#]
+        );
+        assert_eq!(format_value(&Value::Blob(vec![])), "X''");
+    }
+
+    #[test]
+    fn test_tabs_mode_escapes_values() {
+        let output = Output {
+            mode: OutputMode::Tabs,
+            headers: true,
+            ..Output::default()
+        };
+        let result = QueryResult {
+            columns: vec!["id".to_owned(), "name".to_owned()],
+            rows: vec![
+                vec![Value::Int(1), Value::Text("tab\there".to_owned())],
+                vec![
+                    Value::Int(2),
+                    Value::Text("line\nbreak \\ slash".to_owned()),
+                ],
+            ],
+        };
+        assert_eq!(
+            output.render(&result),
+            vec!["id\tname", "1\ttab\\there", "2\tline\\nbreak \\\\ slash"]
+        );
+    }
+
+    #[test]
+    fn test_embedded_nul_in_text() {
+        let db = open_fixture("text_values.db");
+        let mut output = Output::default();
+        let sql = "SELECT id, label FROM labels WHERE id = 2";
+
+        // The text after the NUL is kept
+        assert_eq!(lines(&db, &output, sql), vec!["2|nul\0inside"]);
+        output.mode = OutputMode::Json;
+        assert_eq!(
+            lines(&db, &output, sql),
+            vec![r#"[{"id":2,"label":"nul\u0000inside"}]"#]
+        );
+        output.mode = OutputMode::Tabs;
+        assert_eq!(lines(&db, &output, sql), vec!["2\tnul\\0inside"]);
+    }
+}
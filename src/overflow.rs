@@ -0,0 +1,97 @@
+use anyhow::{bail, Result};
+
+/// Number of bytes of a table btree leaf cell's payload stored on the page itself.
+/// The rest of the payload spills onto a chain of overflow pages.
+/// [overflow](https://www.sqlite.org/fileformat.html#cell_payload_overflow_pages)
+pub fn table_local_payload_size(payload_size: usize, usable_size: usize) -> usize {
+    // The largest payload that still fits entirely in the cell
+    let max_local = usable_size - 35;
+    local_payload_size(payload_size, usable_size, max_local)
+}
+
+/// Number of bytes of an index btree cell's payload stored on the page itself.
+/// Index cells keep less locally than table leaf cells so that an index page always fits
+/// at least four cells.
+pub fn index_local_payload_size(payload_size: usize, usable_size: usize) -> usize {
+    let max_local = (usable_size - 12) * 64 / 255 - 23;
+    local_payload_size(payload_size, usable_size, max_local)
+}
+
+fn local_payload_size(payload_size: usize, usable_size: usize, max_local: usize) -> usize {
+    if payload_size <= max_local {
+        return payload_size;
+    }
+
+    let min_local = (usable_size - 12) * 32 / 255 - 23;
+    // Keep as much locally as lets the overflow pages be filled completely
+    let local = min_local + (payload_size - min_local) % (usable_size - 4);
+    if local <= max_local {
+        local
+    } else {
+        min_local
+    }
+}
+
+/// Read the payload of a cell starting at `stream`, of which `local_size` bytes are stored in
+/// the cell. The rest is read from the chain of overflow pages, fetched with `read_page`.
+pub fn read_payload(
+    stream: &[u8],
+    payload_size: usize,
+    local_size: usize,
+    usable_size: usize,
+    mut read_page: impl FnMut(usize) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let mut payload = stream[..local_size].to_vec();
+    if local_size == payload_size {
+        return Ok(payload);
+    }
+
+    // Each overflow page starts with the number of the next one, 0 for the last
+    let mut next_page = u32::from_be_bytes(stream[local_size..local_size + 4].try_into()?);
+    while next_page != 0 && payload.len() < payload_size {
+        let page = read_page(next_page as usize)?;
+        let size = (payload_size - payload.len()).min(usable_size - 4);
+        payload.extend_from_slice(&page[4..4 + size]);
+        next_page = u32::from_be_bytes(page[..4].try_into()?);
+    }
+
+    if payload.len() < payload_size {
+        bail!(
+            "Overflow chain ended after {} of {} payload bytes",
+            payload.len(),
+            payload_size
+        );
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_local_payload_size_at_boundary() {
+        // 4096 - 35 bytes is the most a cell can keep locally
+        assert_eq!(table_local_payload_size(4060, 4096), 4060);
+        assert_eq!(table_local_payload_size(4061, 4096), 4061);
+        // One byte more overflows, and only the minimum stays local
+        assert_eq!(table_local_payload_size(4062, 4096), 489);
+    }
+
+    #[test]
+    fn test_table_local_payload_size_fills_overflow_pages() {
+        // min local + one full overflow page of 4092 bytes fits locally exactly
+        assert_eq!(table_local_payload_size(489 + 4092 + 100, 4096), 589);
+        assert_eq!(table_local_payload_size(1000, 1024), 103);
+    }
+
+    #[test]
+    fn test_index_local_payload_size() {
+        // (4096 - 12) * 64 / 255 - 23 bytes is the most an index cell keeps locally
+        assert_eq!(index_local_payload_size(1002, 4096), 1002);
+        assert_eq!(index_local_payload_size(1003, 4096), 489);
+        // A payload the table formula would keep entirely on the page
+        assert_eq!(index_local_payload_size(1500, 4096), 489);
+        assert_eq!(index_local_payload_size(300, 1024), 103);
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{bail, Result};
+
+/// Match a value against a LIKE pattern.
+/// `%` matches any sequence of characters and `_` matches a single character.
+/// ASCII characters are compared case insensitively like sqlite does by default.
+/// The optional escape character makes the character following it match literally.
+pub fn like(value: &str, pattern: &str, escape: Option<&str>) -> Result<bool> {
+    let escape = match escape {
+        Some(escape) => {
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Some(ch),
+                _ => bail!("ESCAPE expression must be a single character"),
+            }
+        }
+        None => None,
+    };
+
+    let value: Vec<char> = value.chars().collect();
+    let mut tokens = vec![];
+    let mut pattern = pattern.chars();
+    while let Some(ch) = pattern.next() {
+        let token = match ch {
+            // An escape at the very end of the pattern can never match
+            ch if Some(ch) == escape => match pattern.next() {
+                Some(literal) => Token::single(move |v| v.eq_ignore_ascii_case(&literal)),
+                None => return Ok(false),
+            },
+            // A run of `%` matches the same as one
+            '%' if matches!(tokens.last(), Some(Token::AnySequence)) => continue,
+            '%' => Token::AnySequence,
+            '_' => Token::single(|_| true),
+            ch => Token::single(move |v| v.eq_ignore_ascii_case(&ch)),
+        };
+        tokens.push(token);
+    }
+
+    Ok(wildcard_match(&value, &tokens))
+}
+
+/// A part of a LIKE or GLOB pattern.
+enum Token {
+    AnySequence,
+    Single(Box<dyn Fn(char) -> bool>), // a single character it accepts
+}
+
+impl Token {
+    fn single(matches: impl Fn(char) -> bool + 'static) -> Self {
+        Token::Single(Box::new(matches))
+    }
+}
+
+/// Match a value against the tokens of a pattern. Only the last `AnySequence` is ever
+/// backtracked to, letting it take one more character, so the time is at worst the length
+/// of the value times the length of the pattern.
+fn wildcard_match(value: &[char], tokens: &[Token]) -> bool {
+    let (mut v, mut t) = (0, 0);
+    // The token after the last `AnySequence` and where in the value it was tried
+    let mut backtrack = None;
+    while v < value.len() {
+        match tokens.get(t) {
+            Some(Token::AnySequence) => {
+                t += 1;
+                backtrack = Some((t, v));
+            }
+            Some(Token::Single(matches)) if matches(value[v]) => {
+                v += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((after_sequence, start)) => {
+                    backtrack = Some((after_sequence, start + 1));
+                    (t, v) = (after_sequence, start + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..]
+        .iter()
+        .all(|token| matches!(token, Token::AnySequence))
+}
+
+/// Match a value against a GLOB pattern. Unlike LIKE it is case sensitive.
+/// `*` matches any sequence of characters, `?` matches a single character and `[...]` matches
+/// one of the listed characters or ranges like `a-z`, or any other with `[^...]`.
+pub fn glob(value: &str, pattern: &str) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    let mut tokens = vec![];
+    let mut rest = &pattern[..];
+    while let Some((&ch, after)) = rest.split_first() {
+        rest = after;
+        let token = match ch {
+            // A run of `*` matches the same as one
+            '*' if matches!(tokens.last(), Some(Token::AnySequence)) => continue,
+            '*' => Token::AnySequence,
+            '?' => Token::single(|_| true),
+            '[' => match character_class(rest) {
+                Some((matches, after)) => {
+                    rest = after;
+                    Token::single(matches)
+                }
+                // A `[` without a closing `]` never matches
+                None => return false,
+            },
+            ch => Token::single(move |v| v == ch),
+        };
+        tokens.push(token);
+    }
+
+    wildcard_match(&value, &tokens)
+}
+
+/// Parse the inside of a `[...]` class. Returns whether a character is in the class and the
+/// rest of the pattern after the closing `]`. A `]` right after the `[` or `[^` is literal.
+fn character_class(pattern: &[char]) -> Option<(impl Fn(char) -> bool, &[char])> {
+    let (negated, pattern) = match pattern.first() {
+        Some('^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    let end = pattern.iter().skip(1).position(|&ch| ch == ']')? + 1;
+    let class = &pattern[..end];
+
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            ranges.push((class[i], class[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((class[i], class[i]));
+            i += 1;
+        }
+    }
+
+    let matches =
+        move |ch: char| ranges.iter().any(|&(low, high)| (low..=high).contains(&ch)) != negated;
+    Some((matches, &pattern[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_wildcards() {
+        assert!(like("Google", "goo%", None).unwrap());
+        assert!(like("Google", "%GLE", None).unwrap());
+        assert!(like("Google", "g_o_l_", None).unwrap());
+        assert!(!like("Google", "g_o", None).unwrap());
+        assert!(like("", "%", None).unwrap());
+        assert!(like("abcabd", "%ab_", None).unwrap());
+        assert!(!like("abcabd", "%abc", None).unwrap());
+    }
+
+    #[test]
+    fn test_like_many_wildcards() {
+        // Matched without trying every way of splitting the value between the wildcards
+        let pattern = format!("{}x", "%".repeat(200));
+        assert!(!like(&"a".repeat(1000), &pattern, None).unwrap());
+        assert!(like(&format!("{}x", "a".repeat(1000)), &pattern, None).unwrap());
+        let pattern = format!("{}b", "%a".repeat(100));
+        assert!(!like(&"a".repeat(1000), &pattern, None).unwrap());
+    }
+
+    #[test]
+    fn test_like_escape() {
+        assert!(like("100%", "100\\%", Some("\\")).unwrap());
+        assert!(!like("1000", "100\\%", Some("\\")).unwrap());
+        assert!(like("a_b", "a!_b", Some("!")).unwrap());
+        assert!(!like("axb", "a!_b", Some("!")).unwrap());
+        assert!(like("a!b", "a!!b", Some("!")).unwrap());
+        assert!(!like("abc", "abc!", Some("!")).unwrap());
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob("Google", "Goo*"));
+        assert!(!glob("Google", "goo*"));
+        assert!(glob("Google", "G?ogl?"));
+        assert!(glob("company 7", "company [0-9]"));
+        assert!(!glob("company x", "company [0-9]"));
+        assert!(glob("company x", "company [^0-9]"));
+        assert!(glob("a]", "a[]]"));
+        assert!(!glob("a[", "a["));
+    }
+
+    #[test]
+    fn test_glob_many_wildcards() {
+        let pattern = format!("{}x", "*".repeat(200));
+        assert!(!glob(&"a".repeat(1000), &pattern));
+        assert!(glob(&format!("{}x", "a".repeat(1000)), &pattern));
+        let pattern = format!("{}b", "*[a]".repeat(100));
+        assert!(!glob(&"a".repeat(1000), &pattern));
+    }
+
+    #[test]
+    fn test_like_escape_must_be_single_character() {
+        let err = like("100%", "100ab%", Some("ab")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ESCAPE expression must be a single character"
+        );
+        assert!(like("x", "x", Some("")).is_err());
+    }
+}
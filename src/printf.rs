@@ -0,0 +1,185 @@
+/// Format values like sqlite's `printf(format, ...)`.
+/// Supports the `%d`, `%s`, `%f` and `%x` conversions and `%%`, each with optional `-`, `+`
+/// and `0` flags, a width and a precision. A NULL or missing argument formats as 0 for the
+/// numeric conversions and as empty text for `%s`.
+pub fn printf(format: &str, arguments: &[Option<String>]) -> String {
+    let mut arguments = arguments.iter();
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            output.push(ch);
+            continue;
+        }
+
+        let mut flags = String::new();
+        while let Some(&flag) = chars.peek().filter(|ch| matches!(ch, '-' | '+' | '0')) {
+            flags.push(flag);
+            chars.next();
+        }
+        let width = number(&mut chars);
+        let precision = match chars.peek() {
+            Some('.') => {
+                chars.next();
+                Some(number(&mut chars).unwrap_or(0))
+            }
+            _ => None,
+        };
+
+        // A lone `%` at the end is kept
+        let Some(conversion) = chars.next() else {
+            if flags.is_empty() && width.is_none() && precision.is_none() {
+                output.push('%');
+            }
+            break;
+        };
+        let formatted = match conversion {
+            '%' => {
+                output.push('%');
+                continue;
+            }
+            'd' => {
+                let value = as_integer(arguments.next());
+                let sign = if flags.contains('+') && value >= 0 {
+                    "+"
+                } else {
+                    ""
+                };
+                format!("{}{}", sign, value)
+            }
+            'x' => format!("{:x}", as_integer(arguments.next())),
+            'f' => {
+                let value = as_real(arguments.next());
+                let sign = if flags.contains('+') && value >= 0.0 {
+                    "+"
+                } else {
+                    ""
+                };
+                format!("{}{:.*}", sign, precision.unwrap_or(6), value)
+            }
+            's' => {
+                let value = arguments.next().cloned().flatten().unwrap_or_default();
+                match precision {
+                    Some(precision) => value.chars().take(precision).collect(),
+                    None => value,
+                }
+            }
+            // Like sqlite, an unknown conversion ends the output
+            _ => break,
+        };
+
+        output.push_str(&pad(&formatted, width.unwrap_or(0), &flags, conversion));
+    }
+
+    output
+}
+
+/// Read a decimal number, if there is one.
+fn number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(&digit) = chars.peek().filter(|ch| ch.is_ascii_digit()) {
+        digits.push(digit);
+        chars.next();
+    }
+    digits.parse().ok()
+}
+
+/// Pad a formatted value to the width: with spaces on the left, on the right for the `-`
+/// flag, or with zeros after the sign for the `0` flag of a numeric conversion.
+fn pad(formatted: &str, width: usize, flags: &str, conversion: char) -> String {
+    let padding = width.saturating_sub(formatted.chars().count());
+    if flags.contains('-') {
+        format!("{}{}", formatted, " ".repeat(padding))
+    } else if flags.contains('0') && conversion != 's' {
+        let digits_start = formatted
+            .find(|ch: char| ch != '-' && ch != '+')
+            .unwrap_or(0);
+        let (sign, digits) = formatted.split_at(digits_start);
+        format!("{}{}{}", sign, "0".repeat(padding), digits)
+    } else {
+        format!("{}{}", " ".repeat(padding), formatted)
+    }
+}
+
+/// An argument as an integer, with reals truncated.
+fn as_integer(argument: Option<&Option<String>>) -> i64 {
+    match argument {
+        Some(Some(value)) => value
+            .trim_start()
+            .parse::<i64>()
+            .unwrap_or_else(|_| leading_number(value) as i64),
+        _ => 0,
+    }
+}
+
+/// An argument as a real.
+fn as_real(argument: Option<&Option<String>>) -> f64 {
+    match argument {
+        Some(Some(value)) => leading_number(value),
+        _ => 0.0,
+    }
+}
+
+/// The number at the start of a text, the way sqlite converts text to a number: leading
+/// spaces are skipped and anything after the number is ignored. 0 if there is no number.
+fn leading_number(value: &str) -> f64 {
+    let value = value.trim_start();
+    if !value.starts_with(|ch: char| ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.')) {
+        return 0.0;
+    }
+    (1..=value.len())
+        .rev()
+        .filter(|&end| value.is_char_boundary(end))
+        .find_map(|end| value[..end].parse::<f64>().ok())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[Option<&str>]) -> Vec<Option<String>> {
+        values
+            .iter()
+            .map(|value| value.map(str::to_owned))
+            .collect()
+    }
+
+    #[test]
+    fn test_conversions() {
+        let arguments = args(&[Some("7"), Some("ada"), Some("2.5"), Some("255")]);
+        assert_eq!(
+            printf("%d-%s %f %x 100%%", &arguments),
+            "7-ada 2.500000 ff 100%"
+        );
+        assert_eq!(printf("%d", &args(&[Some("3.9")])), "3");
+        assert_eq!(
+            printf("%d %f", &args(&[Some(" 12abc"), Some("1e3x")])),
+            "12 1000.000000"
+        );
+        assert_eq!(printf("%d", &args(&[Some("abc")])), "0");
+        assert_eq!(printf("%x", &args(&[Some("-1")])), "ffffffffffffffff");
+    }
+
+    #[test]
+    fn test_width_and_precision() {
+        let arguments = args(&[Some("42"), Some("-42"), Some("abcdef"), Some("3.14159")]);
+        assert_eq!(
+            printf("[%5d|%05d|%-8.3s|%.2f]", &arguments),
+            "[   42|-0042|abc     |3.14]"
+        );
+        assert_eq!(
+            printf("%+d %+.1f", &args(&[Some("5"), Some("1")])),
+            "+5 +1.0"
+        );
+    }
+
+    #[test]
+    fn test_null_and_missing_arguments() {
+        assert_eq!(printf("%d|%s|%f", &args(&[None, None])), "0||0.000000");
+        assert_eq!(printf("100%", &[]), "100%");
+        // An unknown conversion ends the output
+        assert_eq!(printf("a%kb", &args(&[Some("1")])), "a");
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{bail, Result};
+
+/// The kind of page a pointer map entry describes.
+/// [pointer map](https://www.sqlite.org/fileformat.html#pointer_map_or_ptrmap_pages)
+#[derive(Debug, PartialEq)]
+pub enum PtrmapType {
+    RootPage = 1,  // a btree root page, the parent is 0
+    FreePage = 2,  // a page on the freelist, the parent is 0
+    Overflow1 = 3, // the first page of an overflow chain, the parent is the btree page
+    Overflow2 = 4, // a later page of an overflow chain, the parent is the previous page
+    BTree = 5,     // a non-root btree page, the parent is its parent btree page
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PtrmapEntry {
+    pub page_number: usize,
+    pub page_type: PtrmapType,
+    pub parent: u32,
+}
+
+/// Number of pages one pointer map page has entries for, 5 bytes each.
+fn entries_per_page(usable_size: usize) -> usize {
+    usable_size / 5
+}
+
+/// Whether the page is a pointer map page. Only databases with auto_vacuum or
+/// incremental_vacuum have them: page 2 is the first and each describes the pages after it up
+/// to the next one.
+pub fn is_ptrmap_page(page_number: usize, usable_size: usize) -> bool {
+    page_number >= 2 && (page_number - 2).is_multiple_of(entries_per_page(usable_size) + 1)
+}
+
+/// Decode the entries of the pointer map page `page_number`. Entries of pages past the end of
+/// the database are all zeros and are left out.
+pub fn parse_ptrmap_page(
+    page: &[u8],
+    page_number: usize,
+    usable_size: usize,
+) -> Result<Vec<PtrmapEntry>> {
+    let mut entries = Vec::new();
+    for (i, entry) in page[..usable_size]
+        .chunks_exact(5)
+        .take(entries_per_page(usable_size))
+        .enumerate()
+    {
+        let page_type = match entry[0] {
+            0 => break,
+            1 => PtrmapType::RootPage,
+            2 => PtrmapType::FreePage,
+            3 => PtrmapType::Overflow1,
+            4 => PtrmapType::Overflow2,
+            5 => PtrmapType::BTree,
+            other => bail!("Invalid pointer map entry type: {}", other),
+        };
+        entries.push(PtrmapEntry {
+            page_number: page_number + 1 + i,
+            page_type,
+            parent: u32::from_be_bytes(entry[1..5].try_into()?),
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ptrmap_page() {
+        // 512 / 5 = 102 entries per pointer map page
+        let pages: Vec<usize> = (1..=320)
+            .filter(|&page| is_ptrmap_page(page, 512))
+            .collect();
+        assert_eq!(pages, vec![2, 105, 208, 311]);
+        assert!(is_ptrmap_page(2, 4096));
+        assert!(is_ptrmap_page(822, 4096));
+        assert!(!is_ptrmap_page(3, 4096));
+    }
+
+    #[test]
+    fn test_parse_ptrmap_page() {
+        let mut page = vec![0; 512];
+        page[..10].copy_from_slice(&[1, 0, 0, 0, 0, 5, 0, 0, 0, 3]);
+        assert_eq!(
+            parse_ptrmap_page(&page, 2, 512).unwrap(),
+            vec![
+                PtrmapEntry {
+                    page_number: 3,
+                    page_type: PtrmapType::RootPage,
+                    parent: 0
+                },
+                PtrmapEntry {
+                    page_number: 4,
+                    page_type: PtrmapType::BTree,
+                    parent: 3
+                },
+            ]
+        );
+
+        page[10] = 9;
+        assert!(parse_ptrmap_page(&page, 2, 512).is_err());
+    }
+}
@@ -1,18 +1,164 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::vec;
 
+use crate::util;
 use crate::varint::parse_varint;
 use anyhow::{bail, Result};
 
 /// Reads SQLite's "Record Format" as mentioned here:
 /// [record_format](https://www.sqlite.org/fileformat.html#record_format)
-pub fn parse_record(stream: &[u8]) -> Result<Vec<Vec<u8>>> {
+/// Every column is decoded to a value according to its serial type.
+pub fn parse_record(stream: &[u8]) -> Result<Vec<Value>> {
+    Ok(parse_record_with_serial_types(stream)?
+        .into_iter()
+        .map(|(serial_type, column)| Value::decode(serial_type, &column))
+        .collect())
+}
+
+/// A decoded column value
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// Decode the bytes of a column with the given serial type.
+    pub fn decode(serial_type: usize, bytes: &[u8]) -> Value {
+        match serial_type {
+            1..=6 | 8 | 9 => Value::Int(parse_be_twos_complement(bytes)),
+            // sqlite reads a stored NaN as NULL
+            7 => match bytes.try_into().map(f64::from_be_bytes) {
+                Ok(float) if !float.is_nan() => Value::Float(float),
+                _ => Value::Null,
+            },
+            n if n >= 12 && n % 2 == 0 => Value::Blob(bytes.to_vec()),
+            n if n >= 13 => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+            _ => Value::Null,
+        }
+    }
+
+    /// The value as sqlite prints it, None for NULL.
+    pub fn into_text(self) -> Option<String> {
+        match self {
+            Value::Null => None,
+            Value::Text(text) => Some(text),
+            value => Some(value.to_string()),
+        }
+    }
+
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+
+    /// Compare two values in the order sqlite sorts them: NULL first, then numbers by their
+    /// value, then text and blobs byte wise.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        let class = |value: &Value| match value {
+            Value::Null => 0,
+            Value::Int(_) | Value::Float(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        };
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Int(a), Value::Float(b)) => (*a as f64).total_cmp(b),
+            (Value::Float(a), Value::Int(b)) => a.total_cmp(&(*b as f64)),
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            (a, b) => class(a).cmp(&class(b)),
+        }
+    }
+}
+
+/// A value that is equal to and ordered against others like `Value::compare`, to key groups,
+/// DISTINCT rows and joins by typed values rather than by how they print. An Int and a Float
+/// with the same numeric value are the same key, and so are two NULLs.
+#[derive(Debug, Clone)]
+pub struct ValueKey(pub Value);
+
+impl PartialEq for ValueKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ValueKey {}
+
+impl PartialOrd for ValueKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ValueKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.compare(&other.0)
+    }
+}
+
+/// Prints a value like the sqlite3 shell: NULL as nothing, a REAL always with a decimal point
+/// or exponent, and the bytes of a blob as they are.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Int(int) => write!(f, "{}", int),
+            Value::Float(float) => f.write_str(&util::format_real(*float)),
+            Value::Text(text) => f.write_str(text),
+            Value::Blob(blob) => f.write_str(&String::from_utf8_lossy(blob)),
+        }
+    }
+}
+
+/// Decode a single column of a record without decoding the columns before it.
+/// Columns past the end of the record (e.g. added later with ALTER TABLE) are NULL.
+pub fn record_column(stream: &[u8], column_index: usize) -> Result<Value> {
+    let (total_bytes, mut offset) = parse_varint(stream);
+    if offset == 0 || total_bytes < offset || total_bytes > stream.len() {
+        bail!("Invalid record header size: {}", total_bytes);
+    }
+
+    // Walk the header, adding up the sizes of the columns before the requested one
+    let mut body_offset = total_bytes;
+    for _ in 0..column_index {
+        if offset >= total_bytes {
+            return Ok(Value::Null);
+        }
+        let (serial_type, read_bytes) = parse_varint(&stream[offset..total_bytes]);
+        offset += read_bytes;
+        body_offset += serial_type_size(serial_type)?;
+    }
+    if offset >= total_bytes {
+        return Ok(Value::Null);
+    }
+
+    let (serial_type, _) = parse_varint(&stream[offset..total_bytes]);
+    let column = parse_column_value(stream.get(body_offset..).unwrap_or_default(), serial_type)?;
+    Ok(Value::decode(serial_type, &column))
+}
+
+/// Reads a record into the serial type and the raw bytes of each column.
+/// Malformed or truncated records are an error rather than a panic.
+fn parse_record_with_serial_types(stream: &[u8]) -> Result<Vec<(usize, Vec<u8>)>> {
     // Parse number of bytes in header, and use bytes_read as offset
     let (total_bytes, mut offset) = parse_varint(stream);
+    if offset == 0 || total_bytes < offset || total_bytes > stream.len() {
+        bail!("Invalid record header size: {}", total_bytes);
+    }
 
     // Read each varint into serial types and modify the offset
     let mut serial_types = vec![];
     while offset < total_bytes {
-        let (varint, read_bytes) = parse_varint(&stream[offset..]);
+        let (varint, read_bytes) = parse_varint(&stream[offset..total_bytes]);
         offset += read_bytes;
         serial_types.push(varint);
     }
@@ -21,38 +167,134 @@ pub fn parse_record(stream: &[u8]) -> Result<Vec<Vec<u8>>> {
     let mut record = vec![];
     for serial_type in serial_types {
         let column = parse_column_value(&stream[offset..], serial_type)?;
-        offset += column.len();
-        record.push(column);
+        // The constant 0 and 1 serial types take no space in the body
+        if !matches!(serial_type, 8 | 9) {
+            offset += column.len();
+        }
+        record.push((serial_type, column));
     }
 
     Ok(record)
 }
 
+/// Decode a big endian twos-complement integer of up to 8 bytes, as used by the
+/// integer serial types of the record format.
+pub fn parse_be_twos_complement(bytes: &[u8]) -> i64 {
+    let sign = if bytes.first().is_some_and(|byte| byte & 0x80 != 0) {
+        -1
+    } else {
+        0
+    };
+    bytes
+        .iter()
+        .fold(sign, |value, &byte| (value << 8) | byte as i64)
+}
+
 fn parse_column_value(stream: &[u8], serial_type: usize) -> Result<Vec<u8>> {
-    let column_value = match serial_type {
-        0 => vec![],
-        // 8 bit twos-complement integer
-        1 => vec![stream[0]],
-        2 => vec![stream[0], stream[1]],
-        3 => vec![stream[0], stream[1], stream[2]],
-        4 => vec![stream[0], stream[1], stream[2], stream[3]],
+    let size = serial_type_size(serial_type)?;
+
+    let Some(bytes) = stream.get(..size) else {
+        bail!(
+            "Record is truncated: column needs {} bytes, only {} left",
+            size,
+            stream.len()
+        );
+    };
+
+    Ok(match serial_type {
         8 => vec![0],
         9 => vec![1],
-        // Text encoding
-        n if serial_type >= 13 && serial_type % 2 == 1 => {
-            let mut n_bytes = (n - 13) / 2;
-            if stream.len() < n_bytes as usize {
-                n_bytes = stream.len() as usize;
-            }
-            let bytes = stream[0..n_bytes as usize].to_vec();
-            bytes
-        }
-        n if serial_type >= 12 && serial_type % 2 == 0 => {
-            let n_bytes = (n - 12) / 2;
-            stream[0..n_bytes as usize].to_vec()
-        }
+        _ => bytes.to_vec(),
+    })
+}
+
+/// Number of bytes a column of the serial type takes up in the record body.
+fn serial_type_size(serial_type: usize) -> Result<usize> {
+    Ok(match serial_type {
+        0 | 8 | 9 => 0,
+        // 8, 16, 24 and 32 bit twos-complement integers
+        1..=4 => serial_type,
+        // 48 bit twos-complement integer
+        5 => 6,
+        // 64 bit twos-complement integer and 64 bit IEEE 754 float
+        6 | 7 => 8,
+        // Blobs (even) and text (odd)
+        n if n >= 12 => (n - 12) / 2,
         _ => bail!("Invalid serial_type: {}", serial_type),
-    };
-    Ok(column_value)
+    })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        // Header of 5 bytes: NULL, 8 bit integer, constant 1, 3 byte text
+        let record = parse_record(&[5, 0, 1, 9, 19, 0xff, b'a', b'b', b'c']).unwrap();
+        assert_eq!(
+            record,
+            vec![
+                Value::Null,
+                Value::Int(-1),
+                Value::Int(1),
+                Value::Text("abc".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display_like_sqlite() {
+        let printed = [
+            Value::Null,
+            Value::Int(-7),
+            Value::Float(2.0),
+            Value::Float(1e20),
+            Value::Text("abc".to_owned()),
+            Value::Blob(b"xyz".to_vec()),
+        ]
+        .map(|value| value.to_string());
+        assert_eq!(printed, ["", "-7", "2.0", "1.0e+20", "abc", "xyz"]);
+    }
+
+    #[test]
+    fn test_record_column() {
+        // NULL, 16 bit integer, 3 byte text, 2 byte blob, constant 0, float
+        let record = [
+            7, 0, 2, 19, 16, 8, 7, 0x01, 0x00, b'a', b'b', b'c', 0xde, 0xad, 0x40, 0x04, 0, 0, 0,
+            0, 0, 0,
+        ];
+        let expected = [
+            Value::Null,
+            Value::Int(256),
+            Value::Text("abc".to_owned()),
+            Value::Blob(vec![0xde, 0xad]),
+            Value::Int(0),
+            Value::Float(2.5),
+        ];
+
+        assert_eq!(parse_record(&record).unwrap(), expected);
+        for (i, value) in expected.iter().enumerate() {
+            assert_eq!(record_column(&record, i).unwrap(), *value, "column {}", i);
+        }
+        assert_eq!(record_column(&record, 6).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_malformed_records_error() {
+        // Found by fuzzing: a header size past the end of the input used to loop forever
+        assert!(parse_record(&[5]).is_err());
+        // Found by fuzzing: a column past the end of the input used to panic
+        assert!(parse_record(&[2, 1]).is_err());
+        assert!(parse_record(&[2, 21, b'a']).is_err());
+        assert!(parse_record(&[]).is_err());
+        assert!(parse_record(&[2, 10]).is_err());
+    }
+
+    #[test]
+    fn test_nan_real_reads_as_null() {
+        // Found by fuzzing: formatting a NaN REAL used to panic
+        let record = parse_record(&[2, 7, 255, 255, 255, 2, 0, 254, 255, 255, 3]).unwrap();
+        assert_eq!(record, vec![Value::Null]);
+    }
+}
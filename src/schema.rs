@@ -1,7 +1,7 @@
 use anyhow::Result;
 use anyhow::Error;
 
-use crate::db::parse_24bit_be_twos_complement;
+use crate::record::Value;
 
 #[derive(Debug)]
 pub struct Schema {
@@ -23,27 +23,30 @@ impl Schema {
     //   rootpage integer,
     //   sql text
     // );
-    pub fn parse_return_option(record: Vec<Vec<u8>>) -> Option<Self> {
-        // dbg!(&record);
+    // Views and triggers have no btree and a rootpage of 0. The indexes sqlite creates for
+    // constraints have no sql, which is read as an empty string.
+    pub fn parse_return_option(record: Vec<Value>) -> Option<Self> {
         let mut items = record.into_iter();
-        let kind = items.next()?;
-        let name = items.next()?;
-        let table_name = items.next()?;
-        let root_page: i64 = parse_24bit_be_twos_complement(&items.next()?);
-        let sql = items.next()?;
+        let kind = items.next()?.into_text()?;
+        let name = items.next()?.into_text()?;
+        let table_name = items.next()?.into_text()?;
+        let Value::Int(root_page) = items.next()? else {
+            return None;
+        };
+        let sql = items.next()?.into_text().unwrap_or_default();
 
         let schema = Self {
-            kind: String::from_utf8_lossy(&kind).to_string(),
-            name: String::from_utf8_lossy(&name).to_string(),
-            table_name: String::from_utf8_lossy(&table_name).to_string(),
+            kind,
+            name,
+            table_name,
             root_page,
-            sql: String::from_utf8_lossy(&sql).to_string(),
+            sql,
         };
         Some(schema)
     }
 
     // convert Option to Result
-    pub fn parse(record: Vec<Vec<u8>>) -> Result<Self> {
-        return Schema::parse_return_option(record).ok_or(Error::msg("Failed to parse schema"))
+    pub fn parse(record: Vec<Value>) -> Result<Self> {
+        Schema::parse_return_option(record).ok_or(Error::msg("Failed to parse schema"))
     }
 }
@@ -1,36 +1,263 @@
-// Parser for SQL statements using peg   
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::record::Value;
+
+// Parser for SQL statements using peg
 peg::parser! {
     grammar sql_parser() for str {
+        pub rule statement() -> Statement
+            = query:select_statement() { Statement::Select(Box::new(query)) }
+            / pragma:pragma_statement() { Statement::Pragma(pragma) }
+
+        rule pragma_statement() -> Pragma =
+            kw("PRAGMA") ws() name:identifier()
+            argument:(wsz() "(" wsz() argument:identifier() wsz() ")" { argument })? {
+                Pragma { name, argument }
+            }
+
         pub rule select_statement() -> Sql
             = kw("SELECT") ws()
-            select_clause:select_clause() ws()
+            distinct:(kw("DISTINCT") ws())?
+            select_clause:select_list() ws()
             kw("FROM") ws()
-            table: identifier()
+            table: table_name()
+            join:join_clause()?
             where_clause:optional_where_clause()?
-            { Sql { select_clause, table, where_clause } }
+            group_by:optional_group_by()?
+            order_by:optional_order_by()?
+            window:optional_limit()?
+            {
+                Sql {
+                    distinct: distinct.is_some(),
+                    select_clause,
+                    table,
+                    join,
+                    where_clause,
+                    group_by: group_by.unwrap_or_default(),
+                    order_by: order_by.unwrap_or_default(),
+                    limit: window.and_then(|(limit, _)| limit),
+                    offset: window.and_then(|(_, offset)| offset),
+                }
+            }
+
+        rule select_list() -> Vec<(SelectItem, Option<String>)> =
+            items: (result_column() ++ list_separator()) { items }
+
+        rule result_column() -> (SelectItem, Option<String>)
+            = "*" { (SelectItem::All, None) }
+            / item:select_item() alias:(ws() kw("AS") ws() alias:identifier() { alias })? { (item, alias) }
+
+        rule select_item() -> SelectItem
+            = expression:arithmetic() { SelectItem::Expression(expression) }
+            / call:scalar_call() { SelectItem::ScalarCall(call) }
+            / call:function_call() { SelectItem::FunctionCall(call) }
+            / name:column_ref() { SelectItem::Column(name) }
+
+        rule function_call() -> FunctionCall
+            = name:identifier() wsz() "(" wsz() "*" wsz() ")" {
+                FunctionCall { name, distinct: false, argument: None }
+            }
+            / name:identifier() wsz() "(" wsz() distinct:(kw("DISTINCT") ws())? argument:column_ref() wsz() ")" {
+                FunctionCall { name, distinct: distinct.is_some(), argument: Some(argument) }
+            }
+
+        rule join_clause() -> Join =
+            ws() (kw("INNER") ws())? kw("JOIN") ws() table:table_name() ws()
+            kw("ON") ws() left:column_ref() wsz() "=" wsz() right:column_ref() {
+                Join { table, on: (left, right) }
+            }
 
-        rule select_clause() -> SelectClause
-            = val2:function_call() { SelectClause::FunctionCall(val2) }
-            / val:column_list() { SelectClause::Columns(val) }
+        rule scalar_call() -> ScalarCall =
+            name:scalar_function_name() wsz() "(" wsz() arguments:(expression() ** list_separator()) wsz() ")" {
+                ScalarCall { name, arguments }
+            }
 
-        rule function_call() -> String
-            = name:identifier() "(*)" { name.to_owned() }
+        rule scalar_function_name() -> String =
+            name:identifier() {?
+                if SCALAR_FUNCTIONS.iter().any(|function| name.eq_ignore_ascii_case(function)) {
+                    Ok(name)
+                } else {
+                    Err("scalar function")
+                }
+            }
 
-        rule column_list() -> Vec<String> =
-            column: (identifier() ** ("," wsz())) { column }
-        
+        // An expression with at least one arithmetic operator
+        rule arithmetic() -> Operand =
+            expression:expression() {?
+                match expression {
+                    Operand::Arithmetic { .. } => Ok(expression),
+                    _ => Err("arithmetic"),
+                }
+            }
+
+        // `*`, `/` and `%` bind tighter than `+` and `-`, all of them group from the left
+        rule expression() -> Operand =
+            first:term() rest:(wsz() op:additive_op() wsz() term:term() { (op, term) })* {
+                Operand::fold(first, rest)
+            }
+
+        rule term() -> Operand =
+            first:factor() rest:(wsz() op:multiplicative_op() wsz() factor:factor() { (op, factor) })* {
+                Operand::fold(first, rest)
+            }
+
+        rule factor() -> Operand
+            = "(" wsz() expression:expression() wsz() ")" { expression }
+            / call:scalar_call() { Operand::Call(call) }
+            / operand()
+
+        rule additive_op() -> ArithmeticOp
+            = "+" { ArithmeticOp::Add }
+            / "-" { ArithmeticOp::Subtract }
+
+        rule multiplicative_op() -> ArithmeticOp
+            = "*" { ArithmeticOp::Multiply }
+            / "/" { ArithmeticOp::Divide }
+            / "%" { ArithmeticOp::Remainder }
+
+        rule operand() -> Operand
+            = kw("NULL") !['a'..='z' | 'A'..='Z' | '_' | '0'..='9'] { Operand::Null }
+            / value:literal() { Operand::Literal(value) }
+            / column:column_ref() { Operand::Column(column) }
+
+        // A quote inside the string is escaped by doubling it
         rule quoted_string() -> String =
-            "'" value:$([^'\'']*) "'" { value.to_owned() }
+            "'" value:$(([^'\''] / "''")*) "'" { value.replace("''", "'") }
+
+        rule optional_where_clause() -> WhereExpr =
+            ws() kw("WHERE") ws() expr:or_expr() { expr }
+
+        // AND binds tighter than OR, so an OR is made of AND groups
+        rule or_expr() -> WhereExpr =
+            terms:(and_expr() ++ (ws() kw("OR") ws())) { WhereExpr::or(terms) }
 
-        rule optional_where_clause() -> (String, String) =
-            ws() kw("WHERE") ws() key:identifier() wsz() "=" wsz() value:quoted_string() { (key.to_owned(), value.to_owned()) }
+        rule and_expr() -> WhereExpr =
+            terms:(primary_expr() ++ (ws() kw("AND") ws())) { WhereExpr::and(terms) }
 
-        rule identifier() -> String =
-            s:$(['a'..='z' | 'A'..='Z' | '_']+) { s.to_owned() }
+        rule primary_expr() -> WhereExpr
+            = "(" wsz() expr:or_expr() wsz() ")" { expr }
+            / condition:where_condition() { WhereExpr::Condition(condition) }
 
-        rule ws() = quiet!{[' ' | '\t']+}
+        rule where_condition() -> WhereCondition
+            = column:column_ref() ws() kw("IS") ws() negated:(kw("NOT") ws())? kw("NULL") !['a'..='z' | 'A'..='Z' | '_' | '0'..='9'] {
+                match negated {
+                    Some(_) => WhereCondition::IsNotNull(column),
+                    None => WhereCondition::IsNull(column),
+                }
+            }
+            / column:column_ref() ws() negated:(kw("NOT") ws())? condition:negatable_condition() {
+                let mut condition = condition;
+                *condition.column_mut() = column;
+                match negated {
+                    Some(_) => WhereCondition::Not(Box::new(condition)),
+                    None => condition,
+                }
+            }
+            / key:column_ref() wsz() "=" wsz() value:literal() { WhereCondition::Equals(key, value) }
+            / column:column_ref() wsz() op:compare_op() wsz() value:literal() {
+                WhereCondition::Compare { column, op, value }
+            }
 
-        rule wsz() = quiet!{[' ' | '\t']*}
+        // Conditions that can be negated by writing NOT before their operator.
+        // The column is filled in by where_condition.
+        rule negatable_condition() -> WhereCondition
+            = kw("LIKE") ws() pattern:quoted_string()
+              escape:(ws() kw("ESCAPE") ws() escape:quoted_string() { escape })? {
+                WhereCondition::Like { column: String::new(), pattern, escape }
+            }
+            / kw("GLOB") ws() pattern:quoted_string() {
+                WhereCondition::Glob { column: String::new(), pattern }
+            }
+            / kw("IN") wsz() "(" wsz() values:(literal() ** (wsz() "," wsz())) wsz() ")" {
+                WhereCondition::In(String::new(), values)
+            }
+            / kw("BETWEEN") ws() low:literal() ws() kw("AND") ws() high:literal() {
+                WhereCondition::Between { column: String::new(), low, high }
+            }
+
+        rule compare_op() -> CompareOp
+            = "!=" { CompareOp::NotEqual }
+            / "<>" { CompareOp::NotEqual }
+            / ">=" { CompareOp::GreaterEqual }
+            / "<=" { CompareOp::LessEqual }
+            / ">" { CompareOp::Greater }
+            / "<" { CompareOp::Less }
+
+        // An integer too large for 64 bits is read as a real, like sqlite does
+        rule literal() -> Value
+            = value:quoted_string() { Value::Text(value) }
+            / n:$("-"? (['0'..='9']+ ("." ['0'..='9']*)? / "." ['0'..='9']+)) {?
+                n.parse()
+                    .map(Value::Int)
+                    .or_else(|_| n.parse().map(Value::Float))
+                    .or(Err("number"))
+            }
+
+        rule optional_group_by() -> Vec<String> =
+            ws() kw("GROUP") ws() kw("BY") ws() columns:(column_ref() ++ list_separator()) { columns }
+
+        rule optional_order_by() -> Vec<(SelectItem, bool)> =
+            ws() kw("ORDER") ws() kw("BY") ws() terms:(ordering_term() ++ list_separator()) { terms }
+
+        rule ordering_term() -> (SelectItem, bool) =
+            item:select_item() descending:(ws() descending:direction() { descending })? {
+                (item, descending.unwrap_or(false))
+            }
+
+        rule direction() -> bool
+            = kw("DESC") { true }
+            / kw("ASC") { false }
+
+        rule optional_limit() -> (Option<usize>, Option<usize>)
+            = ws() kw("LIMIT") ws() limit:number() offset:(ws() kw("OFFSET") ws() offset:number() { offset })? {
+                (Some(limit), offset)
+            }
+            // Unlike sqlite, an OFFSET can be given without a LIMIT
+            / ws() kw("OFFSET") ws() offset:number() { (None, Some(offset)) }
+
+        rule number() -> usize =
+            n:$(['0'..='9']+) {? n.parse().or(Err("row count")) }
+
+        // A table name, optionally qualified with the schema of the (only) open database
+        rule table_name() -> String =
+            schema:(schema:identifier() wsz() "." wsz() { schema })? table:identifier() {?
+                match schema {
+                    Some(schema)
+                        if !schema.eq_ignore_ascii_case("main") && !schema.eq_ignore_ascii_case("temp") =>
+                    {
+                        Err("main or temp database")
+                    }
+                    _ => Ok(table),
+                }
+            }
+
+        // A column, optionally behind unary plus operators which leave the value as is.
+        // It can be qualified with its table like `companies.name`.
+        rule column_ref() -> String =
+            ("+" wsz())* table:(table:identifier() "." { table })? name:identifier() {
+                match table {
+                    Some(table) => format!("{}.{}", table, name),
+                    None => name,
+                }
+            }
+
+        // Words like `order` or `count` can be used as names wherever they can't be mistaken
+        // for the keyword. Digits can follow the first character. Double quoted or in
+        // backticks, any name can be used, with the quote doubled to include it.
+        rule identifier() -> String
+            = s:$(['a'..='z' | 'A'..='Z' | '_'] ['a'..='z' | 'A'..='Z' | '_' | '0'..='9']*) { s.to_owned() }
+            / "\"" s:$(([^'"'] / "\"\"")+) "\"" { s.replace("\"\"", "\"") }
+            / "`" s:$(([^'`'] / "``")+) "`" { s.replace("``", "`") }
+            / "[" s:$([^']']+) "]" { s.to_owned() }
+
+        // The comma between list items, with optional whitespace on both sides
+        rule list_separator() = wsz() "," wsz()
+
+        rule ws() = quiet!{[' ' | '\t' | '\n' | '\r']+}
+
+        rule wsz() = quiet!{[' ' | '\t' | '\n' | '\r']*}
 
         rule kw(kw: &'static str) -> () =
             input:$([_]*<{kw.len()}>)
@@ -38,28 +265,873 @@ peg::parser! {
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum SelectItem {
+    All, // `*`, every column of the table
+    Column(String),
+    FunctionCall(FunctionCall),
+    ScalarCall(ScalarCall),
+    Expression(Operand), // arithmetic like `price * quantity`
+}
+
+impl SelectItem {
+    /// The columns the item reads. None for `*`, which reads every column.
+    pub fn columns(&self) -> Option<Vec<&String>> {
+        match self {
+            SelectItem::All => None,
+            SelectItem::Column(column) => Some(vec![column]),
+            SelectItem::FunctionCall(call) => Some(call.argument.iter().collect()),
+            SelectItem::ScalarCall(call) => Some(call.columns()),
+            SelectItem::Expression(expression) => Some(expression.columns()),
+        }
+    }
+
+    /// The columns the item reads, to be resolved in place. Empty for `*`.
+    pub fn columns_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            SelectItem::All => vec![],
+            SelectItem::Column(column) => vec![column],
+            SelectItem::FunctionCall(call) => call.argument.iter_mut().collect(),
+            SelectItem::ScalarCall(call) => call.columns_mut(),
+            SelectItem::Expression(expression) => expression.columns_mut(),
+        }
+    }
+}
+
+// The canonical text of a select item, which names its result column when it has no alias
+impl fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectItem::All => write!(f, "*"),
+            SelectItem::Column(name) => write!(f, "{}", name),
+            SelectItem::FunctionCall(call) => {
+                let distinct = if call.distinct { "DISTINCT " } else { "" };
+                let argument = call.argument.as_deref().unwrap_or("*");
+                write!(f, "{}({}{})", call.name, distinct, argument)
+            }
+            SelectItem::ScalarCall(call) => write!(f, "{}", call),
+            SelectItem::Expression(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+
+/// Scalar functions, which are evaluated for each row and take any number of arguments
+pub const SCALAR_FUNCTIONS: [&str; 6] = [
+    "PRINTF",
+    "FORMAT",
+    "DATE",
+    "DATETIME",
+    "ABS",
+    "JSON_EXTRACT",
+];
+
+// A scalar function call like `printf('%d-%s', id, name)`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScalarCall {
+    pub name: String,
+    pub arguments: Vec<Operand>,
+}
+
+impl ScalarCall {
+    /// The columns the arguments read.
+    pub fn columns(&self) -> Vec<&String> {
+        self.arguments.iter().flat_map(Operand::columns).collect()
+    }
+
+    pub fn columns_mut(&mut self) -> Vec<&mut String> {
+        self.arguments
+            .iter_mut()
+            .flat_map(Operand::columns_mut)
+            .collect()
+    }
+}
+
+impl fmt::Display for ScalarCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arguments = self.arguments.iter().map(ToString::to_string);
+        write!(
+            f,
+            "{}({})",
+            self.name,
+            arguments.collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+// An argument of a scalar function, or a term of an arithmetic expression
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+    Column(String),
+    Literal(Value),
+    Null,
+    Call(ScalarCall),
+    Arithmetic {
+        left: Box<Operand>,
+        op: ArithmeticOp,
+        right: Box<Operand>,
+    },
+}
+
+impl Operand {
+    // Combine operands joined by operators of the same precedence, grouping from the left
+    fn fold(first: Operand, rest: Vec<(ArithmeticOp, Operand)>) -> Operand {
+        rest.into_iter()
+            .fold(first, |left, (op, right)| Operand::Arithmetic {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            })
+    }
+
+    /// The columns the operand reads.
+    pub fn columns(&self) -> Vec<&String> {
+        match self {
+            Operand::Column(column) => vec![column],
+            Operand::Literal(_) | Operand::Null => vec![],
+            Operand::Call(call) => call.columns(),
+            Operand::Arithmetic { left, right, .. } => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+        }
+    }
+
+    pub fn columns_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Operand::Column(column) => vec![column],
+            Operand::Literal(_) | Operand::Null => vec![],
+            Operand::Call(call) => call.columns_mut(),
+            Operand::Arithmetic { left, right, .. } => {
+                let mut columns = left.columns_mut();
+                columns.extend(right.columns_mut());
+                columns
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Column(name) => write!(f, "{}", name),
+            Operand::Literal(Value::Text(value)) => write!(f, "'{}'", value.replace('\'', "''")),
+            Operand::Literal(value) => write!(f, "{}", value.to_text().unwrap_or_default()),
+            Operand::Null => write!(f, "NULL"),
+            Operand::Call(call) => write!(f, "{}", call),
+            Operand::Arithmetic { left, op, right } => {
+                // Only the parentheses needed to keep the grouping are written
+                let term = |operand: &Operand, grouped: fn(u8, u8) -> bool| match operand {
+                    Operand::Arithmetic { op: inner, .. }
+                        if grouped(inner.precedence(), op.precedence()) =>
+                    {
+                        format!("({})", operand)
+                    }
+                    _ => operand.to_string(),
+                };
+                write!(
+                    f,
+                    "{} {} {}",
+                    term(left, |inner, outer| inner < outer),
+                    op,
+                    term(right, |inner, outer| inner <= outer)
+                )
+            }
+        }
+    }
+}
+
+// Arithmetic operator of an expression
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ArithmeticOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl ArithmeticOp {
+    /// How tightly the operator binds, higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            ArithmeticOp::Add | ArithmeticOp::Subtract => 1,
+            ArithmeticOp::Multiply | ArithmeticOp::Divide | ArithmeticOp::Remainder => 2,
+        }
+    }
+}
+
+impl fmt::Display for ArithmeticOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            ArithmeticOp::Add => "+",
+            ArithmeticOp::Subtract => "-",
+            ArithmeticOp::Multiply => "*",
+            ArithmeticOp::Divide => "/",
+            ArithmeticOp::Remainder => "%",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+// An aggregate function call like COUNT(*) or COUNT(DISTINCT col)
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionCall {
+    pub name: String,
+    pub distinct: bool,           // only count each distinct argument value once
+    pub argument: Option<String>, // column the function is applied to. None for `*`
+}
+
+// Ordering comparison operator of a where condition
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompareOp {
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl CompareOp {
+    /// Whether `left op right` holds given how left compares to right.
+    pub fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::NotEqual => ordering.is_ne(),
+            CompareOp::Less => ordering.is_lt(),
+            CompareOp::LessEqual => ordering.is_le(),
+            CompareOp::Greater => ordering.is_gt(),
+            CompareOp::GreaterEqual => ordering.is_ge(),
+        }
+    }
+}
+
+// A condition in the where clause
+#[derive(Debug, PartialEq, Clone)]
+pub enum WhereCondition {
+    Equals(String, Value), // column = value, a string or numeric literal
+    Compare {
+        column: String,
+        op: CompareOp,
+        value: Value,
+    },
+    In(String, Vec<Value>), // column IN (values). An empty list matches nothing
+    Like {
+        column: String,
+        pattern: String,
+        escape: Option<String>, // ESCAPE 'x'. Must be a single character when evaluated
+    },
+    Glob {
+        column: String,
+        pattern: String, // case sensitive, with `*`, `?` and `[...]` wildcards
+    },
+    Between {
+        column: String,
+        low: Value, // inclusive bounds
+        high: Value,
+    },
+    Not(Box<WhereCondition>), // NOT LIKE, NOT GLOB, NOT IN and NOT BETWEEN
+    IsNull(String),
+    IsNotNull(String),
+}
+
+impl WhereCondition {
+    /// The column the condition is checked against.
+    pub fn column(&self) -> &String {
+        match self {
+            WhereCondition::Equals(column, _) => column,
+            WhereCondition::Compare { column, .. } => column,
+            WhereCondition::In(column, _) => column,
+            WhereCondition::Like { column, .. } => column,
+            WhereCondition::Glob { column, .. } => column,
+            WhereCondition::Between { column, .. } => column,
+            WhereCondition::Not(condition) => condition.column(),
+            WhereCondition::IsNull(column) | WhereCondition::IsNotNull(column) => column,
+        }
+    }
+
+    /// The literals the column is compared with.
+    pub fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            WhereCondition::Equals(_, value) | WhereCondition::Compare { value, .. } => vec![value],
+            WhereCondition::In(_, values) => values.iter_mut().collect(),
+            WhereCondition::Between { low, high, .. } => vec![low, high],
+            WhereCondition::Not(condition) => condition.values_mut(),
+            _ => vec![],
+        }
+    }
+
+    pub fn column_mut(&mut self) -> &mut String {
+        match self {
+            WhereCondition::Equals(column, _) => column,
+            WhereCondition::Compare { column, .. } => column,
+            WhereCondition::In(column, _) => column,
+            WhereCondition::Like { column, .. } => column,
+            WhereCondition::Glob { column, .. } => column,
+            WhereCondition::Between { column, .. } => column,
+            WhereCondition::Not(condition) => condition.column_mut(),
+            WhereCondition::IsNull(column) | WhereCondition::IsNotNull(column) => column,
+        }
+    }
+}
+
+// The where clause, conditions combined with AND and OR
+#[derive(Debug, PartialEq, Clone)]
+pub enum WhereExpr {
+    Condition(WhereCondition),
+    And(Vec<WhereExpr>),
+    Or(Vec<WhereExpr>),
+}
+
+impl WhereExpr {
+    /// AND of the terms, just the term itself if there is only one.
+    pub fn and(mut terms: Vec<WhereExpr>) -> WhereExpr {
+        if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            WhereExpr::And(terms)
+        }
+    }
+
+    /// OR of the terms, just the term itself if there is only one.
+    fn or(mut terms: Vec<WhereExpr>) -> WhereExpr {
+        if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            WhereExpr::Or(terms)
+        }
+    }
+
+    /// The conditions that every matching row satisfies, i.e. the ones not under an OR.
+    /// These can narrow down which records need to be read at all.
+    pub fn required_conditions(&self) -> Vec<&WhereCondition> {
+        match self {
+            WhereExpr::Condition(condition) => vec![condition],
+            WhereExpr::And(terms) => terms
+                .iter()
+                .flat_map(|term| term.required_conditions())
+                .collect(),
+            WhereExpr::Or(_) => vec![],
+        }
+    }
+
+    /// The terms of a top level AND, else just the expression itself.
+    pub fn into_conjuncts(self) -> Vec<WhereExpr> {
+        match self {
+            WhereExpr::And(terms) => terms,
+            expr => vec![expr],
+        }
+    }
+
+    /// Every condition in the expression.
+    pub fn conditions(&self) -> Vec<&WhereCondition> {
+        match self {
+            WhereExpr::Condition(condition) => vec![condition],
+            WhereExpr::And(terms) | WhereExpr::Or(terms) => {
+                terms.iter().flat_map(|term| term.conditions()).collect()
+            }
+        }
+    }
+
+    pub fn conditions_mut(&mut self) -> Vec<&mut WhereCondition> {
+        match self {
+            WhereExpr::Condition(condition) => vec![condition],
+            WhereExpr::And(terms) | WhereExpr::Or(terms) => terms
+                .iter_mut()
+                .flat_map(|term| term.conditions_mut())
+                .collect(),
+        }
+    }
+}
+
+// Final sql statement
+#[derive(Debug, PartialEq, Default)]
+pub struct Sql {
+    pub distinct: bool, // SELECT DISTINCT, dedupes the final rows
+    pub select_clause: Vec<(SelectItem, Option<String>)>, // What is selected, with its optional AS alias
+    pub table: String,                                    // table to select from
+    pub join: Option<Join>,                               // optional INNER JOIN of a second table
+    pub where_clause: Option<WhereExpr>,                  // optional where clause
+    pub group_by: Vec<String>, // columns to group the rows by. Empty when there is no GROUP BY
+    pub order_by: Vec<(SelectItem, bool)>, // terms to sort by, with whether they are DESC
+    pub limit: Option<usize>,  // max number of result rows, applied after aggregation
+    pub offset: Option<usize>, // number of result rows to skip before the LIMIT applies
+}
+
+impl Sql {
+    /// Whether the query produces aggregated rows instead of one row per record.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || self
+                .select_clause
+                .iter()
+                .any(|(item, _)| matches!(item, SelectItem::FunctionCall(_)))
+    }
+
+    /// Whether the query only selects `COUNT(*)` of every row of a single table, so the rows
+    /// can be counted without reading them.
+    pub fn counts_all_rows(&self) -> bool {
+        let is_count_all = |item: &SelectItem| match item {
+            SelectItem::FunctionCall(call) => {
+                call.name.eq_ignore_ascii_case("count") && !call.distinct && call.argument.is_none()
+            }
+            _ => false,
+        };
+        !self.select_clause.is_empty()
+            && self
+                .select_clause
+                .iter()
+                .all(|(item, _)| is_count_all(item))
+            && self.join.is_none()
+            && self.where_clause.is_none()
+            && self.group_by.is_empty()
+            && self.order_by.is_empty()
+    }
+
+    /// Names of the result columns: the AS alias, else the item as written like `count(*)`.
+    /// Like sqlite a column is named without its table, unless that name is taken by another
+    /// result column. Names that are still the same get a `:1`, `:2`, ... suffix, so every
+    /// name is distinct.
+    pub fn column_names(&self) -> Vec<String> {
+        let short_names: Vec<String> = self
+            .select_clause
+            .iter()
+            .map(|(item, alias)| match (item, alias) {
+                (_, Some(alias)) => alias.clone(),
+                (SelectItem::Column(column), None) => match column.split_once('.') {
+                    Some((_, name)) => name.to_owned(),
+                    None => column.clone(),
+                },
+                (item, None) => item.to_string(),
+            })
+            .collect();
+
+        let mut names: Vec<String> = short_names
+            .iter()
+            .zip(&self.select_clause)
+            .map(|(name, (item, alias))| {
+                let taken = short_names.iter().filter(|other| *other == name).count() > 1;
+                match (item, alias) {
+                    (SelectItem::Column(column), None) if taken => column.clone(),
+                    _ => name.clone(),
+                }
+            })
+            .collect();
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for name in names.iter_mut() {
+            let count = seen.entry(name.clone()).or_default();
+            if *count > 0 {
+                *name = format!("{}:{}", name, count);
+            }
+            *count += 1;
+        }
+        names
+    }
+
+    /// Every column the query reads, in any clause. None if it selects `*`.
+    /// ORDER BY terms naming an alias are expected to be resolved already.
+    pub fn referenced_columns(&self) -> Option<Vec<&String>> {
+        let mut columns = vec![];
+        let items = self.select_clause.iter().map(|(item, _)| item);
+        for item in items.chain(self.order_by.iter().map(|(item, _)| item)) {
+            columns.extend(item.columns()?);
+        }
+        columns.extend(
+            self.where_clause
+                .iter()
+                .flat_map(|expr| expr.conditions())
+                .map(|condition| condition.column()),
+        );
+        columns.extend(&self.group_by);
+        if let Some(join) = &self.join {
+            columns.extend([&join.on.0, &join.on.1]);
+        }
+        Some(columns)
+    }
+
+    /// The select item named by an AS alias, if any. Like other names, aliases are matched
+    /// ignoring case.
+    pub fn aliased_item(&self, alias: &str) -> Option<&SelectItem> {
+        self.select_clause
+            .iter()
+            .find(|(_, item_alias)| {
+                item_alias
+                    .as_deref()
+                    .is_some_and(|item_alias| item_alias.eq_ignore_ascii_case(alias))
+            })
+            .map(|(item, _)| item)
+    }
+}
+
+// An INNER JOIN of a second table, matching the rows where the two ON columns are equal
+#[derive(Debug, PartialEq, Clone)]
+pub struct Join {
+    pub table: String,
+    pub on: (String, String), // the columns compared by `ON a = b`, in the order written
+}
+
+// A PRAGMA statement like `PRAGMA table_info(companies)`
 #[derive(Debug, PartialEq)]
-pub enum SelectClause {
-    Columns(Vec<String>),
-    FunctionCall(String),
+pub struct Pragma {
+    pub name: String,
+    pub argument: Option<String>, // the parenthesized argument, if any
 }
 
-// Final sql statement 
+// Any statement that can be executed
 #[derive(Debug, PartialEq)]
-pub struct Sql {
-    pub select_clause: SelectClause, // What is selected
-    pub table: String, // table to select from  
-    pub where_clause: Option<(String, String)>, // optional where clause. only support direct string comparison for now  
+pub enum Statement {
+    Select(Box<Sql>),
+    Pragma(Pragma),
+}
+
+pub fn parse_statement(
+    input: &str,
+) -> Result<Statement, peg::error::ParseError<peg::str::LineCol>> {
+    sql_parser::statement(input)
 }
 
 pub fn parse_sql(input: &str) -> Result<Sql, peg::error::ParseError<peg::str::LineCol>> {
     sql_parser::select_statement(input)
 }
 
+/// Parse a `;` separated list of statements. Empty statements are skipped.
+pub fn parse_statements(
+    input: &str,
+) -> Result<Vec<Statement>, peg::error::ParseError<peg::str::LineCol>> {
+    split_statements(input)
+        .into_iter()
+        .map(parse_statement)
+        .collect()
+}
+
+/// Split input on top level semicolons.
+/// Semicolons inside single quoted string literals ('' being an escaped quote) and inside
+/// double quoted or backtick quoted identifiers don't split.
+pub fn split_statements(input: &str) -> Vec<&str> {
+    let mut statements = vec![];
+    let mut quote = None; // the quote the input is inside of
+    let mut start = 0;
+
+    // An escaped quote ('' or "") just leaves and enters the quote again, so it needs no
+    // special casing. Other kinds of quotes inside a quote are plain characters.
+    for (i, ch) in input.char_indices() {
+        match ch {
+            '\'' | '"' | '`' if quote.is_none() => quote = Some(ch),
+            ch if quote == Some(ch) => quote = None,
+            ';' if quote.is_none() => {
+                statements.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&input[start..]);
+
+    statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_owned())
+    }
+
+    fn column(name: &str) -> (SelectItem, Option<String>) {
+        (SelectItem::Column(name.to_owned()), None)
+    }
+
+    fn count(distinct: bool, argument: Option<&str>) -> SelectItem {
+        SelectItem::FunctionCall(FunctionCall {
+            name: "COUNT".to_owned(),
+            distinct,
+            argument: argument.map(str::to_owned),
+        })
+    }
+
+    #[test]
+    fn test_counts_all_rows() {
+        let counts_all_rows = |sql: &str| parse_sql(sql).unwrap().counts_all_rows();
+        assert!(counts_all_rows("SELECT COUNT(*) FROM t"));
+        assert!(counts_all_rows(
+            "SELECT count(*) AS n, COUNT(*) FROM t LIMIT 1"
+        ));
+        assert!(!counts_all_rows("SELECT COUNT(a) FROM t"));
+        assert!(!counts_all_rows("SELECT COUNT(*), MAX(a) FROM t"));
+        assert!(!counts_all_rows("SELECT COUNT(*) FROM t WHERE a = 1"));
+        assert!(!counts_all_rows("SELECT COUNT(*) FROM t GROUP BY a"));
+    }
+
+    #[test]
+    fn test_column_aliases() {
+        let sql = parse_sql("SELECT name AS company_name, domain FROM companies").unwrap();
+        assert_eq!(
+            sql.select_clause,
+            vec![
+                (
+                    SelectItem::Column("name".to_owned()),
+                    Some("company_name".to_owned())
+                ),
+                column("domain"),
+            ]
+        );
+        assert_eq!(sql.column_names(), vec!["company_name", "domain"]);
+        assert!(parse_sql("SELECT name AS FROM companies").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_column_names() {
+        let names = |sql: &str| parse_sql(sql).unwrap().column_names();
+
+        assert_eq!(
+            names("SELECT a.id, b.id, b.title FROM a JOIN b ON a.id = b.a_id"),
+            vec!["a.id", "b.id", "title"]
+        );
+        assert_eq!(
+            names("SELECT id, id, name AS id FROM a"),
+            vec!["id", "id:1", "id:2"]
+        );
+        assert_eq!(
+            names("SELECT COUNT(*), count(*) FROM a"),
+            vec!["COUNT(*)", "count(*)"]
+        );
+    }
+
+    #[test]
+    fn test_scalar_call() {
+        let sql = parse_sql("SELECT printf('%d-%s', id,name, NULL, 5, nullable) FROM t").unwrap();
+        let item = SelectItem::ScalarCall(ScalarCall {
+            name: "printf".to_owned(),
+            arguments: vec![
+                Operand::Literal(Value::Text("%d-%s".to_owned())),
+                Operand::Column("id".to_owned()),
+                Operand::Column("name".to_owned()),
+                Operand::Null,
+                Operand::Literal(Value::Int(5)),
+                Operand::Column("nullable".to_owned()),
+            ],
+        });
+        assert_eq!(sql.select_clause, vec![(item, None)]);
+        assert_eq!(
+            sql.column_names(),
+            vec!["printf('%d-%s', id, name, NULL, 5, nullable)"]
+        );
+        assert!(!sql.is_aggregate());
+
+        // Other functions are aggregates
+        let sql = parse_sql("SELECT upper(name) FROM t").unwrap();
+        assert!(sql.is_aggregate());
+    }
+
+    #[test]
+    fn test_arithmetic_expressions() {
+        let sql = parse_sql("SELECT id*2 + 1 FROM t").unwrap();
+        let column = |name: &str| Box::new(Operand::Column(name.to_owned()));
+        let expression = Operand::Arithmetic {
+            left: Box::new(Operand::Arithmetic {
+                left: column("id"),
+                op: ArithmeticOp::Multiply,
+                right: Box::new(Operand::Literal(Value::Int(2))),
+            }),
+            op: ArithmeticOp::Add,
+            right: Box::new(Operand::Literal(Value::Int(1))),
+        };
+        assert_eq!(
+            sql.select_clause,
+            vec![(SelectItem::Expression(expression), None)]
+        );
+
+        let sql = parse_sql(
+            "SELECT (a - b) - c, a - (b - c), (a + b) * c, abs(x - 10) / 2, a-1 AS d FROM t ORDER BY a % 3",
+        )
+        .unwrap();
+        assert_eq!(
+            sql.column_names(),
+            vec![
+                "a - b - c",
+                "a - (b - c)",
+                "(a + b) * c",
+                "abs(x - 10) / 2",
+                "d"
+            ]
+        );
+        assert_eq!(sql.order_by[0].0.to_string(), "a % 3");
+        assert_eq!(
+            sql.referenced_columns().unwrap(),
+            vec!["a", "b", "c", "a", "b", "c", "a", "b", "c", "x", "a", "a"]
+        );
+        assert!(!sql.is_aggregate());
+    }
+
+    #[test]
+    fn test_join() {
+        let sql =
+            parse_sql("SELECT a.x, y FROM a INNER JOIN b ON a.id = b.a_id WHERE b.z = 1").unwrap();
+        assert_eq!(sql.select_clause, vec![column("a.x"), column("y")]);
+        assert_eq!(
+            sql.join,
+            Some(Join {
+                table: "b".to_owned(),
+                on: ("a.id".to_owned(), "b.a_id".to_owned()),
+            })
+        );
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "b.z".to_owned(),
+                Value::Int(1)
+            )))
+        );
+        assert!(parse_sql("SELECT x FROM a JOIN b").is_err());
+    }
+
+    #[test]
+    fn test_qualified_columns() {
+        let sql = parse_sql(
+            "SELECT t.a, COUNT(DISTINCT t.b) FROM t WHERE t.c = 1 GROUP BY t.a ORDER BY t.a",
+        )
+        .unwrap();
+        assert_eq!(
+            sql.select_clause,
+            vec![column("t.a"), (count(true, Some("t.b")), None)]
+        );
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "t.c".to_owned(),
+                Value::Int(1)
+            )))
+        );
+        assert_eq!(sql.group_by, vec!["t.a"]);
+        assert_eq!(
+            sql.order_by,
+            vec![(SelectItem::Column("t.a".to_owned()), false)]
+        );
+    }
+
+    #[test]
+    fn test_conditions_joined_by_and() {
+        let sql =
+            parse_sql("SELECT a FROM t WHERE a = 1 and b BETWEEN 2 AND 3 AND c LIKE 'x'").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::And(vec![
+                WhereExpr::Condition(WhereCondition::Equals("a".to_owned(), Value::Int(1))),
+                WhereExpr::Condition(WhereCondition::Between {
+                    column: "b".to_owned(),
+                    low: Value::Int(2),
+                    high: Value::Int(3),
+                }),
+                WhereExpr::Condition(WhereCondition::Like {
+                    column: "c".to_owned(),
+                    pattern: "x".to_owned(),
+                    escape: None,
+                }),
+            ]))
+        );
+        assert!(parse_sql("SELECT a FROM t WHERE a = 1 AND").is_err());
+    }
+
+    #[test]
+    fn test_or_binds_looser_than_and() {
+        let equals = |column: &str, value: i64| {
+            WhereExpr::Condition(WhereCondition::Equals(column.to_owned(), Value::Int(value)))
+        };
+
+        let sql = parse_sql("SELECT a FROM t WHERE a = 1 OR b = 2 AND c = 3 or d = 4").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Or(vec![
+                equals("a", 1),
+                WhereExpr::And(vec![equals("b", 2), equals("c", 3)]),
+                equals("d", 4),
+            ]))
+        );
+
+        let sql = parse_sql("SELECT a FROM t WHERE (a = 1 OR b = 2) AND c = 3").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::And(vec![
+                WhereExpr::Or(vec![equals("a", 1), equals("b", 2)]),
+                equals("c", 3),
+            ]))
+        );
+        let required: Vec<&String> = sql
+            .where_clause
+            .as_ref()
+            .unwrap()
+            .required_conditions()
+            .into_iter()
+            .map(|condition| condition.column())
+            .collect();
+        assert_eq!(required, vec!["c"]);
+    }
+
+    #[test]
+    fn test_column_list_separators() {
+        let sql = parse_sql("SELECT a , b,c ,d FROM t GROUP BY a ,b ORDER BY c , d").unwrap();
+        assert_eq!(
+            sql.select_clause,
+            vec![column("a"), column("b"), column("c"), column("d")]
+        );
+        assert_eq!(sql.group_by, vec!["a", "b"]);
+        assert_eq!(sql.order_by.len(), 2);
+
+        assert!(parse_sql("SELECT a, b, FROM t").is_err());
+        assert!(parse_sql("SELECT a, b , FROM t").is_err());
+        assert!(parse_sql("SELECT a,, b FROM t").is_err());
+    }
+
+    #[test]
+    fn test_long_column_list() {
+        let columns = vec!["name"; 500];
+        let sql = parse_sql(&format!("SELECT {} FROM t", columns.join(", "))).unwrap();
+        assert_eq!(sql.select_clause.len(), 500);
+    }
+
+    #[test]
+    fn test_negated_conditions() {
+        let where_clause = |sql: &str| match parse_sql(sql).unwrap().where_clause {
+            Some(WhereExpr::Condition(condition)) => condition,
+            where_clause => panic!("not a single condition: {:?}", where_clause),
+        };
+        assert_eq!(
+            where_clause("SELECT a FROM t WHERE a NOT LIKE 'x%'"),
+            WhereCondition::Not(Box::new(WhereCondition::Like {
+                column: "a".to_owned(),
+                pattern: "x%".to_owned(),
+                escape: None,
+            }))
+        );
+        assert_eq!(
+            where_clause("SELECT a FROM t WHERE a not between 1 and 'z'"),
+            WhereCondition::Not(Box::new(WhereCondition::Between {
+                column: "a".to_owned(),
+                low: Value::Int(1),
+                high: text("z"),
+            }))
+        );
+        assert_eq!(
+            where_clause("SELECT a FROM t WHERE a GLOB 'x*'"),
+            WhereCondition::Glob {
+                column: "a".to_owned(),
+                pattern: "x*".to_owned(),
+            }
+        );
+        assert_eq!(
+            where_clause("SELECT a FROM t WHERE a NOT IN (1)"),
+            WhereCondition::Not(Box::new(WhereCondition::In(
+                "a".to_owned(),
+                vec![Value::Int(1)]
+            )))
+        );
+    }
+
     #[test]
     fn test_simple() {
         struct TestCase(&'static str, Sql);
@@ -68,54 +1140,473 @@ mod tests {
             TestCase(
                 "SELECT one FROM apples",
                 Sql {
-                    select_clause: SelectClause::Columns(vec!["one".to_string()]),
+                    select_clause: vec![column("one")],
                     table: "apples".to_owned(),
-                    where_clause: None,
+                    ..Default::default()
                 },
             ),
             TestCase(
                 "SELECT one FROM apples WHERE key = 'value'",
                 Sql {
-                    select_clause: SelectClause::Columns(vec!["one".to_string()]),
+                    select_clause: vec![column("one")],
                     table: "apples".to_owned(),
-                    where_clause: Some(("key".to_owned(), "value".to_owned())),
+                    where_clause: Some(WhereExpr::Condition(WhereCondition::Equals(
+                        "key".to_owned(),
+                        text("value"),
+                    ))),
+                    ..Default::default()
                 },
             ),
             TestCase(
                 "SELECT one(*) FROM apples",
                 Sql {
-                    select_clause: SelectClause::FunctionCall("one".to_string()),
+                    select_clause: vec![(
+                        SelectItem::FunctionCall(FunctionCall {
+                            name: "one".to_string(),
+                            distinct: false,
+                            argument: None,
+                        }),
+                        None,
+                    )],
                     table: "apples".to_owned(),
-                    where_clause: None,
+                    ..Default::default()
                 },
             ),
             TestCase(
                 "SELECT one, two FROM apples",
                 Sql {
-                    select_clause: SelectClause::Columns(vec![
-                        "one".to_string(),
-                        "two".to_string(),
-                    ]),
+                    select_clause: vec![column("one"), column("two")],
                     table: "apples".to_owned(),
-                    where_clause: None,
+                    ..Default::default()
                 },
             ),
             TestCase(
                 "select one, two fRoM apples",
                 Sql {
-                    select_clause: SelectClause::Columns(vec![
-                        "one".to_string(),
-                        "two".to_string(),
-                    ]),
+                    select_clause: vec![column("one"), column("two")],
+                    table: "apples".to_owned(),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT one, COUNT(*) FROM apples WHERE key = 'value' GROUP BY one LIMIT 3",
+                Sql {
+                    select_clause: vec![column("one"), (count(false, None), None)],
+                    table: "apples".to_owned(),
+                    where_clause: Some(WhereExpr::Condition(WhereCondition::Equals(
+                        "key".to_owned(),
+                        text("value"),
+                    ))),
+                    group_by: vec!["one".to_owned()],
+                    limit: Some(3),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT COUNT(*) FROM apples limit 0",
+                Sql {
+                    select_clause: vec![(count(false, None), None)],
+                    table: "apples".to_owned(),
+                    limit: Some(0),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT DISTINCT country, COUNT(DISTINCT industry) FROM t GROUP BY country",
+                Sql {
+                    distinct: true,
+                    select_clause: vec![column("country"), (count(true, Some("industry")), None)],
+                    table: "t".to_owned(),
+                    group_by: vec!["country".to_owned()],
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT one FROM apples WHERE name LIKE '100!%' ESCAPE '!'",
+                Sql {
+                    select_clause: vec![column("one")],
                     table: "apples".to_owned(),
-                    where_clause: None,
+                    where_clause: Some(WhereExpr::Condition(WhereCondition::Like {
+                        column: "name".to_owned(),
+                        pattern: "100!%".to_owned(),
+                        escape: Some("!".to_owned()),
+                    })),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT one FROM apples LIMIT 1 OFFSET 20",
+                Sql {
+                    select_clause: vec![column("one")],
+                    table: "apples".to_owned(),
+                    limit: Some(1),
+                    offset: Some(20),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT * FROM apples",
+                Sql {
+                    select_clause: vec![(SelectItem::All, None)],
+                    table: "apples".to_owned(),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT one FROM apples OFFSET 20",
+                Sql {
+                    select_clause: vec![column("one")],
+                    table: "apples".to_owned(),
+                    offset: Some(20),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT name AS n, COUNT(*) AS total FROM apples GROUP BY n ORDER BY total DESC, n LIMIT 2",
+                Sql {
+                    select_clause: vec![
+                        (SelectItem::Column("name".to_owned()), Some("n".to_owned())),
+                        (count(false, None), Some("total".to_owned())),
+                    ],
+                    table: "apples".to_owned(),
+                    group_by: vec!["n".to_owned()],
+                    order_by: vec![
+                        (SelectItem::Column("total".to_owned()), true),
+                        (SelectItem::Column("n".to_owned()), false),
+                    ],
+                    limit: Some(2),
+                    ..Default::default()
+                },
+            ),
+            TestCase(
+                "SELECT name FROM apples ORDER BY COUNT(*) asc",
+                Sql {
+                    select_clause: vec![column("name")],
+                    table: "apples".to_owned(),
+                    order_by: vec![(count(false, None), false)],
+                    ..Default::default()
                 },
             ),
         ];
 
         for tc in test_cases {
             println!("\n\nrunning tests for [{}]", tc.0);
-            assert_eq!(parse_sql(&tc.0).unwrap(), tc.1,);
+            assert_eq!(parse_sql(tc.0).unwrap(), tc.1,);
+        }
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("SELECT a FROM t; SELECT b FROM t;\n"),
+            vec!["SELECT a FROM t", "SELECT b FROM t"]
+        );
+        assert_eq!(
+            split_statements("SELECT a FROM t WHERE x = 'a;b'; SELECT b FROM t"),
+            vec!["SELECT a FROM t WHERE x = 'a;b'", "SELECT b FROM t"]
+        );
+        assert_eq!(
+            split_statements("SELECT a FROM t WHERE x = 'it''s; fine'"),
+            vec!["SELECT a FROM t WHERE x = 'it''s; fine'"]
+        );
+        // Quotes and semicolons in quoted identifiers
+        assert_eq!(
+            split_statements(r#"SELECT "it's;" FROM t; SELECT `a;'b` FROM t; SELECT 'x"' FROM t"#),
+            vec![
+                r#"SELECT "it's;" FROM t"#,
+                "SELECT `a;'b` FROM t",
+                r#"SELECT 'x"' FROM t"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_statements() {
+        let statements =
+            parse_statements("SELECT a FROM t WHERE x = 'a;b';\nSELECT COUNT(*)\nFROM t;").unwrap();
+
+        assert_eq!(
+            statements,
+            vec![
+                Statement::Select(Box::new(Sql {
+                    select_clause: vec![column("a")],
+                    table: "t".to_owned(),
+                    where_clause: Some(WhereExpr::Condition(WhereCondition::Equals(
+                        "x".to_owned(),
+                        text("a;b")
+                    ))),
+                    ..Default::default()
+                })),
+                Statement::Select(Box::new(Sql {
+                    select_clause: vec![(count(false, None), None)],
+                    table: "t".to_owned(),
+                    ..Default::default()
+                })),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_condition() {
+        let sql = parse_sql("SELECT id FROM t WHERE rowid > 1000 ORDER BY rowid LIMIT 50").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Compare {
+                column: "rowid".to_owned(),
+                op: CompareOp::Greater,
+                value: Value::Int(1000),
+            }))
+        );
+
+        let sql = parse_sql("SELECT id FROM t WHERE price < -1.5").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Compare {
+                column: "price".to_owned(),
+                op: CompareOp::Less,
+                value: Value::Float(-1.5),
+            }))
+        );
+        let sql = parse_sql("SELECT id FROM t WHERE id = 99999999999999999999").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "id".to_owned(),
+                Value::Float(1e20)
+            )))
+        );
+
+        let sql = parse_sql("SELECT id FROM t WHERE name<='b'").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Compare {
+                column: "name".to_owned(),
+                op: CompareOp::LessEqual,
+                value: text("b"),
+            }))
+        );
+
+        // Both spellings of not equal
+        for sql in [
+            "SELECT id FROM t WHERE name != 'b'",
+            "SELECT id FROM t WHERE name <> 'b'",
+            "SELECT id FROM t WHERE name<>'b'",
+        ] {
+            assert_eq!(
+                parse_sql(sql).unwrap().where_clause,
+                Some(WhereExpr::Condition(WhereCondition::Compare {
+                    column: "name".to_owned(),
+                    op: CompareOp::NotEqual,
+                    value: text("b"),
+                }))
+            );
         }
     }
+
+    #[test]
+    fn test_escaped_quotes_in_strings() {
+        let sql = parse_sql("SELECT id FROM t WHERE name = 'O''Brien'").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "name".to_owned(),
+                text("O'Brien")
+            )))
+        );
+        let sql = parse_sql("SELECT id FROM t WHERE name IN ('''', '', 'a''''b')").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::In(
+                "name".to_owned(),
+                vec![text("'"), text(""), text("a''b")]
+            )))
+        );
+        assert!(parse_sql("SELECT id FROM t WHERE name = 'O'Brien'").is_err());
+
+        // Written back escaped, as in the names of result columns
+        let sql = parse_sql("SELECT printf('it''s %d', id) FROM t").unwrap();
+        assert_eq!(sql.column_names(), vec!["printf('it''s %d', id)"]);
+    }
+
+    #[test]
+    fn test_keyword_and_quoted_names() {
+        let sql = parse_sql(
+            "SELECT \"order\", count, \"a \"\"b\"\"\" AS \"x\", t.\"group\" FROM \"my table\" WHERE select = 1",
+        )
+        .unwrap();
+        assert_eq!(
+            sql.select_clause,
+            vec![
+                column("order"),
+                column("count"),
+                (
+                    SelectItem::Column("a \"b\"".to_owned()),
+                    Some("x".to_owned())
+                ),
+                column("t.group"),
+            ]
+        );
+        assert_eq!(sql.table, "my table");
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "select".to_owned(),
+                Value::Int(1)
+            )))
+        );
+
+        // Unquoted keywords still work as keywords where a keyword fits
+        let sql = parse_sql("SELECT order FROM t ORDER BY order DESC").unwrap();
+        assert_eq!(sql.select_clause, vec![column("order")]);
+        assert_eq!(
+            sql.order_by,
+            vec![(SelectItem::Column("order".to_owned()), true)]
+        );
+        assert!(parse_sql("SELECT \"\" FROM t").is_err());
+    }
+
+    #[test]
+    fn test_names_with_digits_and_backticks() {
+        let sql =
+            parse_sql("SELECT col1, _x2, \"size range\", `a ``b`` c` FROM t1 WHERE null1 IS NULL")
+                .unwrap();
+        assert_eq!(
+            sql.select_clause,
+            vec![
+                column("col1"),
+                column("_x2"),
+                column("size range"),
+                column("a `b` c")
+            ]
+        );
+        assert_eq!(sql.table, "t1");
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::IsNull(
+                "null1".to_owned()
+            )))
+        );
+        // A name can't start with a digit
+        assert!(parse_sql("SELECT 1col FROM t").is_err());
+    }
+
+    #[test]
+    fn test_bracketed_names() {
+        let sql =
+            parse_sql("SELECT [size range], [a\"b`c] FROM [my table] WHERE [x y] = 'z'").unwrap();
+        assert_eq!(
+            sql.select_clause,
+            vec![column("size range"), column("a\"b`c")]
+        );
+        assert_eq!(sql.table, "my table");
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "x y".to_owned(),
+                Value::Text("z".to_owned())
+            )))
+        );
+        assert!(parse_sql("SELECT [] FROM t").is_err());
+    }
+
+    #[test]
+    fn test_schema_qualified_table() {
+        assert_eq!(
+            parse_sql("SELECT name FROM main.companies").unwrap(),
+            parse_sql("SELECT name FROM companies").unwrap()
+        );
+        assert_eq!(parse_sql("SELECT name FROM TEMP.t").unwrap().table, "t");
+        assert!(parse_sql("SELECT name FROM other.companies").is_err());
+    }
+
+    #[test]
+    fn test_is_null_condition() {
+        let sql = parse_sql("SELECT a FROM t WHERE a IS NULL AND b is not NULL").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::And(vec![
+                WhereExpr::Condition(WhereCondition::IsNull("a".to_owned())),
+                WhereExpr::Condition(WhereCondition::IsNotNull("b".to_owned())),
+            ]))
+        );
+        assert!(parse_sql("SELECT a FROM t WHERE a IS NULLS").is_err());
+        assert!(parse_sql("SELECT a FROM t WHERE a IS 1").is_err());
+    }
+
+    #[test]
+    fn test_unary_plus() {
+        let sql = parse_sql("SELECT +name, + + id FROM t WHERE +x = 5").unwrap();
+        assert_eq!(sql.select_clause, vec![column("name"), column("id")]);
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::Equals(
+                "x".to_owned(),
+                Value::Int(5)
+            )))
+        );
+    }
+
+    #[test]
+    fn test_in_condition() {
+        let sql = parse_sql("SELECT id FROM t WHERE country IN ('usa', 'uk',1)").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::In(
+                "country".to_owned(),
+                vec![text("usa"), text("uk"), Value::Int(1)]
+            )))
+        );
+
+        let sql = parse_sql("SELECT id FROM t WHERE country IN ( )").unwrap();
+        assert_eq!(
+            sql.where_clause,
+            Some(WhereExpr::Condition(WhereCondition::In(
+                "country".to_owned(),
+                vec![]
+            )))
+        );
+    }
+
+    #[test]
+    fn test_ast_dump() {
+        let statement = parse_statement(
+            "SELECT country AS c, COUNT(*) FROM companies WHERE name > 'b' GROUP BY country ORDER BY c DESC LIMIT 3",
+        )
+        .unwrap();
+        let dump = format!("{:#?}", statement);
+
+        for node in [
+            "Select(",
+            "Column(",
+            "\"country\"",
+            "FunctionCall(",
+            "name: \"COUNT\"",
+            "table: \"companies\"",
+            "Compare {",
+            "op: Greater",
+            "group_by: [",
+            "order_by: [",
+            "limit: Some(",
+        ] {
+            assert!(dump.contains(node), "{} not in {}", node, dump);
+        }
+    }
+
+    #[test]
+    fn test_parse_pragma() {
+        assert_eq!(
+            parse_statement("PRAGMA table_info(companies)").unwrap(),
+            Statement::Pragma(Pragma {
+                name: "table_info".to_owned(),
+                argument: Some("companies".to_owned()),
+            })
+        );
+        assert_eq!(
+            parse_statement("pragma page_size").unwrap(),
+            Statement::Pragma(Pragma {
+                name: "page_size".to_owned(),
+                argument: None,
+            })
+        );
+    }
 }
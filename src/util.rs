@@ -1,23 +1,209 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use itertools::Itertools;
 use std::fs::File;
 use std::os::unix::fs::FileExt;
 
-use crate::creation_sql::Field;
+use crate::creation_sql::{Affinity, Field};
 use crate::db::Record;
+use crate::record::Value;
 
 /// Read nth page from file   
 pub fn read_page(file: &File, page_size: u16, page: usize) -> Result<Vec<u8>> {
+    // Pages are numbered from 1, a page number past the end of the file is corruption
+    let page_count = file.metadata()?.len() / page_size as u64;
+    if page == 0 || page as u64 > page_count {
+        bail!("Page {} is out of range 1 to {}", page, page_count);
+    }
     let mut buffer = vec![0; page_size as usize];
     file.read_exact_at(&mut buffer, page_size as u64 * (page - 1) as u64)?;
     Ok(buffer)
 }
 
+/// Format bytes as a hex dump. Each line has the offset, 16 bytes in hex and their
+/// printable ascii characters.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let hex = chunk.iter().map(|byte| format!("{:02x}", byte)).join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:06x}: {:<47}  {}", line * 16, hex, ascii)
+        })
+        .join("\n")
+}
+
+/// Format a REAL the way sqlite prints it (`%!.15g`): 15 significant digits without trailing
+/// zeros but always with a decimal point, in exponent notation when the exponent is below -4
+/// or at least 15.
+pub fn format_real(value: f64) -> String {
+    if value == 0.0 {
+        return "0.0".to_owned();
+    }
+    // sqlite reads a stored NaN as NULL
+    if value.is_nan() {
+        return String::new();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Inf" } else { "-Inf" }.to_owned();
+    }
+
+    // Round to 15 significant digits
+    let scientific = format!("{:.14e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+    let digits = digits.trim_end_matches('0');
+    let sign = if value < 0.0 { "-" } else { "" };
+
+    if !(-4..15).contains(&exponent) {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        format!(
+            "{}{}.{}e{}{:02}",
+            sign,
+            first,
+            rest,
+            exponent_sign,
+            exponent.abs()
+        )
+    } else if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        format!("{}0.{}{}", sign, zeros, digits)
+    } else {
+        let integer_digits = exponent as usize + 1;
+        if digits.len() > integer_digits {
+            let (integer, fraction) = digits.split_at(integer_digits);
+            format!("{}{}.{}", sign, integer, fraction)
+        } else {
+            let zeros = "0".repeat(integer_digits - digits.len());
+            format!("{}{}{}.0", sign, digits, zeros)
+        }
+    }
+}
+
+/// Quote a string as a JSON string literal, escaping quotes, backslashes and control characters.
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 /// If the column is an INTEGER PRIMARY KEY then its values will be NULL in the
 /// fields and should be picked from row_id.
-pub fn get_value_for_record(record: &Record, ind: usize, field: &Field) -> String {
+/// The NULL still takes up a slot in the record, so `ind` is the column's position in the
+/// CREATE TABLE statement wherever the key is declared.
+pub fn get_value_for_record(record: &Record, ind: usize, field: &Field) -> Value {
     if field.is_primary_key {
-        return record.row_id.clone();
+        return Value::Int(record.row_id);
     }
 
-    return record.columns[ind].clone();
-}
\ No newline at end of file
+    match &record.columns[ind] {
+        // Integral values of REAL columns are stored as integers to save space
+        Value::Int(int) if field.has_real_affinity() => Value::Float(*int as f64),
+        value => value.clone(),
+    }
+}
+
+/// Convert a literal compared with a column of the given affinity, like sqlite does before
+/// comparing: text that looks like a number becomes that number for a numeric column, and a
+/// number becomes text for a TEXT column. Otherwise the literal is left as it is.
+pub fn apply_affinity(literal: &Value, affinity: Affinity) -> Value {
+    match (affinity, literal) {
+        (Affinity::Integer | Affinity::Real | Affinity::Numeric, Value::Text(text)) => {
+            let text = text.trim();
+            text.parse()
+                .map(Value::Int)
+                .ok()
+                .or_else(|| {
+                    text.parse()
+                        .ok()
+                        .filter(|number: &f64| number.is_finite())
+                        .map(Value::Float)
+                })
+                .unwrap_or_else(|| literal.clone())
+        }
+        (Affinity::Text, Value::Int(_) | Value::Float(_)) => Value::Text(literal.to_string()),
+        _ => literal.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_real() {
+        let cases = [
+            (2.0, "2.0"),
+            (100.0, "100.0"),
+            (-2.5, "-2.5"),
+            (0.1, "0.1"),
+            (1.0 / 3.0, "0.333333333333333"),
+            (0.30000000000000004, "0.3"),
+            (0.0001, "0.0001"),
+            (1.5e-5, "1.5e-05"),
+            (1e14, "100000000000000.0"),
+            (1e15, "1.0e+15"),
+            (123456789012345.6, "123456789012346.0"),
+            (1e-300, "1.0e-300"),
+            (-0.0, "0.0"),
+            (f64::NAN, ""),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(format_real(value), expected, "formatting {}", value);
+        }
+    }
+
+    #[test]
+    fn test_apply_affinity() {
+        let text = |value: &str| Value::Text(value.to_owned());
+        assert_eq!(apply_affinity(&text("4"), Affinity::Integer), Value::Int(4));
+        assert_eq!(
+            apply_affinity(&text(" 2.5"), Affinity::Numeric),
+            Value::Float(2.5)
+        );
+        assert_eq!(apply_affinity(&text("four"), Affinity::Real), text("four"));
+        assert_eq!(apply_affinity(&text("inf"), Affinity::Real), text("inf"));
+        assert_eq!(apply_affinity(&Value::Int(10), Affinity::Text), text("10"));
+        assert_eq!(
+            apply_affinity(&Value::Float(2.5), Affinity::Text),
+            text("2.5")
+        );
+        // Without affinity nothing is converted
+        assert_eq!(apply_affinity(&text("10"), Affinity::Blob), text("10"));
+        assert_eq!(
+            apply_affinity(&Value::Int(10), Affinity::Blob),
+            Value::Int(10)
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("plain"), r#""plain""#);
+        assert_eq!(json_string("a \"b\"\\"), r#""a \"b\"\\""#);
+        assert_eq!(json_string("tab\tline\n\u{1}"), r#""tab\tline\n\u0001""#);
+    }
+}
@@ -13,26 +13,21 @@ pub fn parse_varint(stream: &[u8]) -> (usize, usize) {
         .into_iter()
         .enumerate()
         .fold(0, |value, (i, usable_byte)| {
-            let usable_size = if i == 8 { 8 } else { 7 };
-            (value << usable_size) + usable_value(usable_size, usable_byte) as usize
+            // The ninth byte contributes all of its 8 bits
+            if i == 8 {
+                (value << 8) | usable_byte as usize
+            } else {
+                (value << 7) | (usable_byte & LAST_SEVEN_BITS_MASK) as usize
+            }
         });
     (varint, bytes_read)
 }
 
-/// Usable size is either 8 or 7
-fn usable_value(usable_size: u8, byte: u8) -> u8 {
-    if usable_size == 8 {
-        usable_size
-    } else {
-        byte & LAST_SEVEN_BITS_MASK
-    }
-}
-
 fn read_usable_bytes(stream: &[u8]) -> Vec<u8> {
     let mut usable_bytes = vec![];
 
-    for i in 0..8 {
-        let byte = stream[i];
+    // A varint is at most 9 bytes long
+    for &byte in stream.iter().take(9) {
         usable_bytes.push(byte);
         if starts_with_zero(byte) {
             break;
@@ -44,4 +39,27 @@ fn read_usable_bytes(stream: &[u8]) -> Vec<u8> {
 
 fn starts_with_zero(byte: u8) -> bool {
     (byte & IS_FIRST_BIT_ZERO_MASK) == 0
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_varint() {
+        assert_eq!(parse_varint(&[0x7f]), (127, 1));
+        assert_eq!(parse_varint(&[0x81, 0x00]), (128, 2));
+        // Nine bytes, the last one holding a full 8 bits
+        assert_eq!(parse_varint(&[0xff; 9]), (u64::MAX as usize, 9));
+        assert_eq!(
+            parse_varint(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]),
+            (1, 9)
+        );
+    }
+
+    #[test]
+    fn test_parse_varint_truncated() {
+        assert_eq!(parse_varint(&[]), (0, 0));
+        assert_eq!(parse_varint(&[0x81]), (1, 1));
+    }
+}